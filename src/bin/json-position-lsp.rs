@@ -0,0 +1,441 @@
+//! A language server for json and jsonc files, speaking LSP over stdio.
+use json_position_parser::edit::position_at;
+use json_position_parser::editor::{DocumentSymbol, FoldingKind, ValueKind};
+use json_position_parser::format::{format_edits, FormatOptions};
+use json_position_parser::refs::{Loader, Workspace};
+use json_position_parser::types::{ParseError, Position, Range};
+use json_position_parser::{parse_json, parse_json_tolerant};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types as lsp;
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentSymbolRequest, FoldingRangeRequest, Formatting, GotoDefinition, HoverRequest,
+    References, SelectionRangeRequest,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::{fs, io};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+type Documents = HashMap<lsp::Url, String>;
+
+/// Convert a position to LSP, which counts utf-16 code units in a line.
+fn to_lsp_position(text: &str, position: Position) -> lsp::Position {
+    let idx = position.idx.min(text.len());
+    let line_start = text[..idx].rfind('\n').map_or(0, |pos| pos + 1);
+    let character = text[line_start..idx].encode_utf16().count();
+    lsp::Position::new(position.line as u32, character as u32)
+}
+
+fn to_lsp_range(text: &str, range: Range) -> lsp::Range {
+    lsp::Range::new(
+        to_lsp_position(text, range.start),
+        to_lsp_position(text, range.end),
+    )
+}
+
+fn from_lsp_position(text: &str, position: lsp::Position) -> Position {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(|line| line.len())
+        .sum();
+
+    let mut units = 0;
+    let idx = text[line_start..]
+        .char_indices()
+        .find(|(_, c)| {
+            let found = *c == '\n' || units >= position.character as usize;
+            units += c.len_utf16();
+            found
+        })
+        .map_or(text.len(), |(idx, _)| line_start + idx);
+    position_at(text, idx)
+}
+
+fn diagnostics(text: &str) -> Vec<lsp::Diagnostic> {
+    let error = match parse_json(text) {
+        Ok(_) => return vec![],
+        Err(error) => error,
+    };
+    let range = match error {
+        ParseError::UnexpectedCharacter(range)
+        | ParseError::UnterminatedLiteral(range)
+        | ParseError::TrailingContent(range) => range,
+        _ => {
+            let end = position_at(text, text.len());
+            Range::new(end, end)
+        }
+    };
+
+    vec![lsp::Diagnostic {
+        range: to_lsp_range(text, range),
+        severity: Some(lsp::DiagnosticSeverity::ERROR),
+        source: Some("json".to_owned()),
+        message: error.to_string(),
+        ..lsp::Diagnostic::default()
+    }]
+}
+
+#[allow(deprecated)]
+fn to_lsp_symbol(text: &str, symbol: DocumentSymbol) -> lsp::DocumentSymbol {
+    let kind = match symbol.kind {
+        ValueKind::Object => lsp::SymbolKind::OBJECT,
+        ValueKind::Array => lsp::SymbolKind::ARRAY,
+        ValueKind::String => lsp::SymbolKind::STRING,
+        ValueKind::Integer | ValueKind::Number => lsp::SymbolKind::NUMBER,
+        ValueKind::Boolean => lsp::SymbolKind::BOOLEAN,
+        ValueKind::Null => lsp::SymbolKind::NULL,
+    };
+    let children: Vec<lsp::DocumentSymbol> = symbol
+        .children
+        .into_iter()
+        .map(|child| to_lsp_symbol(text, child))
+        .collect();
+
+    lsp::DocumentSymbol {
+        name: symbol.name,
+        detail: Some(symbol.detail),
+        kind,
+        tags: None,
+        deprecated: None,
+        range: to_lsp_range(text, symbol.range),
+        selection_range: to_lsp_range(text, symbol.selection_range),
+        children: if children.is_empty() {
+            None
+        } else {
+            Some(children)
+        },
+    }
+}
+
+fn document_symbols(text: &str) -> Vec<lsp::DocumentSymbol> {
+    parse_json_tolerant(text)
+        .document_symbols()
+        .into_iter()
+        .map(|symbol| to_lsp_symbol(text, symbol))
+        .collect()
+}
+
+fn folding_ranges(text: &str) -> Vec<lsp::FoldingRange> {
+    parse_json_tolerant(text)
+        .folding_ranges(text)
+        .into_iter()
+        .map(|range| lsp::FoldingRange {
+            start_line: range.start_line as u32,
+            end_line: range.end_line as u32,
+            kind: Some(match range.kind {
+                FoldingKind::Region => lsp::FoldingRangeKind::Region,
+                FoldingKind::Comment => lsp::FoldingRangeKind::Comment,
+            }),
+            ..lsp::FoldingRange::default()
+        })
+        .collect()
+}
+
+/// The ranges around a position from the innermost to the root: the key or
+/// value, the key with its value, the containing object, and so on.
+fn selection_range(text: &str, position: lsp::Position) -> lsp::SelectionRange {
+    let tree = parse_json_tolerant(text);
+    let position = from_lsp_position(text, position);
+    let mut ranges = tree.selection_ranges(position);
+    if ranges.is_empty() {
+        ranges.push(Range::new(position, position));
+    }
+
+    ranges
+        .into_iter()
+        .rev()
+        .fold(None, |parent, range| {
+            Some(lsp::SelectionRange {
+                range: to_lsp_range(text, range),
+                parent: parent.map(Box::new),
+            })
+        })
+        .unwrap_or_default()
+}
+
+fn hover(text: &str, position: lsp::Position) -> Option<lsp::Hover> {
+    let tree = parse_json_tolerant(text);
+    let hover = tree.hover(text, from_lsp_position(text, position))?;
+    let mut value = format!(
+        "`{}` ({}): `{}`",
+        hover.path,
+        hover.kind.name(),
+        hover.preview
+    );
+    if let Some(comment) = hover.comment {
+        value.push_str("\n\n");
+        value.push_str(&comment);
+    }
+
+    Some(lsp::Hover {
+        contents: lsp::HoverContents::Markup(lsp::MarkupContent {
+            kind: lsp::MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(to_lsp_range(text, hover.range)),
+    })
+}
+
+/// Load a file of a `$ref` from the open documents, or else from disk.
+fn load(documents: &Documents, file: &str) -> io::Result<String> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, file.to_owned());
+    let uri = lsp::Url::parse(file).map_err(|_| invalid())?;
+    match documents.get(&uri) {
+        Some(text) => Ok(text.clone()),
+        None => fs::read_to_string(uri.to_file_path().map_err(|_| invalid())?),
+    }
+}
+
+fn to_location<L: Loader>(
+    workspace: &Workspace<L>,
+    file: &str,
+    range: Range,
+) -> Option<lsp::Location> {
+    let text = &workspace.document(file)?.text;
+    Some(lsp::Location::new(
+        lsp::Url::parse(file).ok()?,
+        to_lsp_range(text, range),
+    ))
+}
+
+/// Go to the target of the `$ref` at a position.
+fn definition(
+    documents: &Documents,
+    uri: &lsp::Url,
+    position: lsp::Position,
+) -> Option<lsp::Location> {
+    let mut workspace = Workspace::new(|file: &str| load(documents, file));
+    workspace.load(uri.as_str()).ok()?;
+    let document = workspace.document(uri.as_str())?;
+    let position = from_lsp_position(&document.text, position);
+    let (entry, _) = document.tree.entry_at(position)?;
+
+    let target = workspace.resolve_ref(uri.as_str(), entry).ok()?;
+    to_location(&workspace, target.file, target.range)
+}
+
+/// Find the `$ref`s to the value at a position, in the open documents and
+/// the files they reference.
+fn references(
+    documents: &Documents,
+    uri: &lsp::Url,
+    position: lsp::Position,
+) -> Option<Vec<lsp::Location>> {
+    let mut workspace = Workspace::new(|file: &str| load(documents, file));
+    documents.keys().for_each(|uri| {
+        let _ = workspace.load(uri.as_str());
+    });
+    let document = workspace.document(uri.as_str())?;
+    let position = from_lsp_position(&document.text, position);
+    let (entry, _) = document.tree.entry_at(position)?;
+
+    let index = workspace.reference_index();
+    Some(
+        index
+            .references_to(uri.as_str(), entry)
+            .iter()
+            .filter_map(|reference| to_location(&workspace, &reference.file, reference.range))
+            .collect(),
+    )
+}
+
+fn formatting(text: &str, options: &lsp::FormattingOptions) -> Option<Vec<lsp::TextEdit>> {
+    let options = FormatOptions {
+        indent_width: options.tab_size as usize,
+        use_tabs: !options.insert_spaces,
+        ..FormatOptions::default()
+    };
+
+    format_edits(text, &options).ok().map(|edits| {
+        edits
+            .into_iter()
+            .map(|edit| lsp::TextEdit::new(to_lsp_range(text, edit.range), edit.new_text))
+            .collect()
+    })
+}
+
+/// Get the params of a request for `R`, or the error response for params
+/// which do not fit `R`.
+fn params<R: lsp::request::Request>(request: &Request) -> std::result::Result<R::Params, Response> {
+    serde_json::from_value(request.params.clone()).map_err(|error| {
+        Response::new_err(
+            request.id.clone(),
+            ErrorCode::InvalidParams as i32,
+            format!("Invalid params for {}: {}", R::METHOD, error),
+        )
+    })
+}
+
+fn handle_request(documents: &Documents, request: Request) -> Response {
+    match request_result(documents, &request) {
+        Ok(result) => Response::new_ok(request.id, result),
+        Err(response) => response,
+    }
+}
+
+fn request_result(
+    documents: &Documents,
+    request: &Request,
+) -> std::result::Result<serde_json::Value, Response> {
+    use lsp_types::request::Request as _;
+    let text = |uri: &lsp::Url| documents.get(uri).map(|text| text.as_str());
+
+    let result = match request.method.as_str() {
+        DocumentSymbolRequest::METHOD => {
+            let p = params::<DocumentSymbolRequest>(request)?;
+            serde_json::to_value(text(&p.text_document.uri).map(document_symbols))
+        }
+        FoldingRangeRequest::METHOD => {
+            let p = params::<FoldingRangeRequest>(request)?;
+            serde_json::to_value(text(&p.text_document.uri).map(folding_ranges))
+        }
+        SelectionRangeRequest::METHOD => {
+            let p = params::<SelectionRangeRequest>(request)?;
+            serde_json::to_value(text(&p.text_document.uri).map(|text| {
+                p.positions
+                    .iter()
+                    .map(|position| selection_range(text, *position))
+                    .collect::<Vec<_>>()
+            }))
+        }
+        HoverRequest::METHOD => {
+            let position = params::<HoverRequest>(request)?.text_document_position_params;
+            serde_json::to_value(
+                text(&position.text_document.uri).and_then(|text| hover(text, position.position)),
+            )
+        }
+        GotoDefinition::METHOD => {
+            let position = params::<GotoDefinition>(request)?.text_document_position_params;
+            serde_json::to_value(definition(
+                documents,
+                &position.text_document.uri,
+                position.position,
+            ))
+        }
+        References::METHOD => {
+            let position = params::<References>(request)?.text_document_position;
+            serde_json::to_value(references(
+                documents,
+                &position.text_document.uri,
+                position.position,
+            ))
+        }
+        Formatting::METHOD => {
+            let p = params::<Formatting>(request)?;
+            serde_json::to_value(
+                text(&p.text_document.uri).and_then(|text| formatting(text, &p.options)),
+            )
+        }
+        _ => {
+            return Err(Response::new_err(
+                request.id.clone(),
+                ErrorCode::MethodNotFound as i32,
+                format!("Unknown method {}", request.method),
+            ))
+        }
+    };
+
+    result.map_err(|error| {
+        Response::new_err(
+            request.id.clone(),
+            ErrorCode::InternalError as i32,
+            error.to_string(),
+        )
+    })
+}
+
+/// Update the documents and get the uri of the document to publish
+/// diagnostics for.
+fn handle_notification(documents: &mut Documents, notification: Notification) -> Option<lsp::Url> {
+    use lsp_types::notification::Notification as _;
+    let params = notification.params;
+
+    match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let p: lsp::DidOpenTextDocumentParams = serde_json::from_value(params).ok()?;
+            documents.insert(p.text_document.uri.clone(), p.text_document.text);
+            Some(p.text_document.uri)
+        }
+        DidChangeTextDocument::METHOD => {
+            let p: lsp::DidChangeTextDocumentParams = serde_json::from_value(params).ok()?;
+            let change = p.content_changes.into_iter().last()?;
+            documents.insert(p.text_document.uri.clone(), change.text);
+            Some(p.text_document.uri)
+        }
+        DidCloseTextDocument::METHOD => {
+            let p: lsp::DidCloseTextDocumentParams = serde_json::from_value(params).ok()?;
+            documents.remove(&p.text_document.uri);
+            None
+        }
+        _ => None,
+    }
+}
+
+fn publish_diagnostics(
+    connection: &Connection,
+    documents: &Documents,
+    uri: lsp::Url,
+) -> Result<()> {
+    use lsp_types::notification::Notification as _;
+    let diagnostics = documents
+        .get(&uri)
+        .map(|text| diagnostics(text))
+        .unwrap_or_default();
+    let params = lsp::PublishDiagnosticsParams::new(uri, diagnostics, None);
+
+    connection
+        .sender
+        .send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_owned(),
+            params,
+        )))?;
+    Ok(())
+}
+
+fn capabilities() -> lsp::ServerCapabilities {
+    lsp::ServerCapabilities {
+        text_document_sync: Some(lsp::TextDocumentSyncCapability::Kind(
+            lsp::TextDocumentSyncKind::FULL,
+        )),
+        document_symbol_provider: Some(lsp::OneOf::Left(true)),
+        folding_range_provider: Some(lsp::FoldingRangeProviderCapability::Simple(true)),
+        selection_range_provider: Some(lsp::SelectionRangeProviderCapability::Simple(true)),
+        hover_provider: Some(lsp::HoverProviderCapability::Simple(true)),
+        definition_provider: Some(lsp::OneOf::Left(true)),
+        references_provider: Some(lsp::OneOf::Left(true)),
+        document_formatting_provider: Some(lsp::OneOf::Left(true)),
+        ..lsp::ServerCapabilities::default()
+    }
+}
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    let mut documents = Documents::new();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                let response = handle_request(&documents, request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(uri) = handle_notification(&mut documents, notification) {
+                    publish_diagnostics(&connection, &documents, uri)?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
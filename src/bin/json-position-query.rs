@@ -0,0 +1,216 @@
+//! Find values in json files by a path query, printing where they are like
+//! `config.json:3:12: 8080`.
+use json_position_parser::parse_json;
+use json_position_parser::tree::{JsonPath, PathQuery, Tree};
+use json_position_parser::types::{ParseResult, Range};
+use json_position_parser::writer::{quote_string, write_entry, WriteOptions};
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+const USAGE: &str = "Usage: json-position-query [OPTIONS] <QUERY> <PATH>...
+
+Print the values at QUERY in json files, as `file:line:col: value`. QUERY is
+a JSONPath like `$.dependencies.lodash` or `$..port`, or a json pointer like
+`/dependencies/lodash`. Directories are searched for `.json` files.
+
+Only files with an object at the root can be searched. Files which can not be
+parsed, like ones with an array at the root, are reported and skipped.
+
+Options:
+  -k, --keys   Print the keys of matching objects instead of the values
+  -j, --json   Print every match as a json object on its own line
+  -h, --help   Print this help
+
+Exit status is 0 if there was a match, 1 if there was none and 2 on errors
+other than skipped files.";
+
+struct Options {
+    keys: bool,
+    json: bool,
+    query: String,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        keys: false,
+        json: false,
+        query: String::new(),
+        paths: vec![],
+    };
+    let mut positional = vec![];
+
+    for arg in args {
+        match arg.as_str() {
+            "-k" | "--keys" => options.keys = true,
+            "-j" | "--json" => options.json = true,
+            "-h" | "--help" => return Err(String::new()),
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("Unknown option {}", flag))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    options.query = positional.next().ok_or("Missing query")?;
+    options.paths = positional.map(PathBuf::from).collect();
+    if options.paths.is_empty() {
+        return Err("Missing path".to_owned());
+    }
+    Ok(options)
+}
+
+/// A value or key found by the query.
+struct Match {
+    range: Range,
+    path: JsonPath,
+    text: String,
+    json: String,
+}
+
+enum Query {
+    Path(PathQuery),
+    Pointer(String),
+}
+
+impl Query {
+    fn parse(query: &str) -> ParseResult<Query> {
+        if query.is_empty() || query.starts_with('/') {
+            Ok(Query::Pointer(query.to_owned()))
+        } else {
+            PathQuery::parse(query).map(Query::Path)
+        }
+    }
+
+    fn matches(&self, tree: &Tree, keys: bool) -> Vec<Match> {
+        let entries = match self {
+            Query::Path(query) => tree.value_at(&query.as_path_types()),
+            Query::Pointer(pointer) => tree.entry_at_pointer(pointer).into_iter().collect(),
+        };
+
+        let mut matches: Vec<Match> = entries
+            .into_iter()
+            .flat_map(|entry| {
+                let path = tree.path_of(entry);
+                if !keys {
+                    let text = write_entry(tree, entry, &WriteOptions::compact());
+                    return vec![Match {
+                        range: entry.source_range(),
+                        path,
+                        json: text.clone(),
+                        text,
+                    }];
+                }
+
+                tree.object_entries(entry)
+                    .into_iter()
+                    .map(|(key, _)| Match {
+                        range: key.source_range(),
+                        path: path.with_key(&key.name),
+                        text: key.name.clone(),
+                        json: quote_string(&key.name),
+                    })
+                    .collect()
+            })
+            .collect();
+        matches.sort_by_key(|m| m.range.start.idx);
+        matches
+    }
+}
+
+/// Collect the files to search, in a stable order.
+fn collect_files(path: &Path, explicit: bool, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        if explicit || path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path.to_owned());
+        }
+        return Ok(());
+    }
+
+    let mut children = fs::read_dir(path)?
+        .map(|child| child.map(|child| child.path()))
+        .collect::<std::io::Result<Vec<PathBuf>>>()?;
+    children.sort();
+    children
+        .iter()
+        .try_for_each(|child| collect_files(child, false, files))
+}
+
+fn print_match(file: &Path, found: &Match, json: bool) {
+    let file = file.display().to_string();
+    if json {
+        println!(
+            "{{\"file\":{},\"line\":{},\"column\":{},\"path\":{},\"pointer\":{},\"value\":{}}}",
+            quote_string(&file),
+            found.range.start.line + 1,
+            found.range.start.char + 1,
+            quote_string(&found.path.to_string()),
+            quote_string(&found.path.to_pointer()),
+            found.json
+        );
+    } else {
+        println!("{}:{}: {}", file, found.range.start, found.text);
+    }
+}
+
+/// Search the files and get the exit status.
+fn run(options: &Options) -> i32 {
+    let query = match Query::parse(&options.query) {
+        Ok(query) => query,
+        Err(e) => {
+            eprintln!("json-position-query: {}", e);
+            return 2;
+        }
+    };
+
+    let mut files = vec![];
+    let mut failed = false;
+    options.paths.iter().for_each(|path| {
+        if let Err(e) = collect_files(path, true, &mut files) {
+            eprintln!("json-position-query: {}: {}", path.display(), e);
+            failed = true;
+        }
+    });
+
+    let mut found = false;
+    files.iter().for_each(|file| {
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("json-position-query: {}: {}", file.display(), e);
+                failed = true;
+                return;
+            }
+        };
+        match parse_json(&text) {
+            Ok(tree) => query.matches(&tree, options.keys).iter().for_each(|m| {
+                found = true;
+                print_match(file, m, options.json);
+            }),
+            Err(e) => eprintln!("json-position-query: {}: skipped: {}", file.display(), e),
+        }
+    });
+
+    match (failed, found) {
+        (true, _) => 2,
+        (false, true) => 0,
+        (false, false) => 1,
+    }
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) if message.is_empty() => {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+        Err(message) => {
+            eprintln!("json-position-query: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    process::exit(run(&options));
+}
@@ -0,0 +1,399 @@
+use super::schema::Schema;
+use super::tree::{Entry, EntryType, Key, Tree};
+use super::types::{Position, Range, TextEdit};
+use super::writer::{quote_string, write_entry, WriteOptions};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompletionKind {
+    Property,
+    Value,
+}
+
+/// A completion candidate with the edit inserting it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// The `description` of the schema the candidate comes from.
+    pub description: Option<String>,
+    pub edit: TextEdit,
+}
+
+fn is_container(entry: &Entry) -> bool {
+    matches!(
+        entry.entry_type,
+        EntryType::JSONObject(_) | EntryType::JSONArray(_)
+    )
+}
+
+fn touches(range: Range, idx: usize) -> bool {
+    range.start.idx <= idx && idx <= range.end.idx
+}
+
+/// The range a value completion replaces when no value touches the cursor: a
+/// broken literal like `fa` at the cursor, or the cursor itself.
+fn insert_range(tree: &Tree, position: Position) -> Range {
+    tree.invalid
+        .iter()
+        .find(|range| touches(**range, position.idx))
+        .copied()
+        .unwrap_or_else(|| Range::new(position, position))
+}
+
+/// A schema and the schemas of its `allOf`, `anyOf` and `oneOf`.
+fn alternatives<'a>(schema: &'a Schema, entry: &'a Entry) -> Vec<&'a Entry> {
+    let entry = schema.resolve(entry);
+    let mut result = vec![entry];
+    ["allOf", "anyOf", "oneOf"].iter().for_each(|name| {
+        if let Some(list) = schema.keyword(entry, name) {
+            result.extend(
+                schema
+                    .tree
+                    .array_entries(list)
+                    .into_iter()
+                    .map(|sub| schema.resolve(sub)),
+            );
+        }
+    });
+    result
+}
+
+fn description(schema: &Schema, entry: &Entry) -> Option<String> {
+    alternatives(schema, entry)
+        .into_iter()
+        .find_map(|entry| schema.keyword_str(entry, "description"))
+        .map(|description| description.to_owned())
+}
+
+/// The text inserted after a new key: the default value of the property or
+/// an empty value of its type.
+fn placeholder(schema: &Schema, entry: &Entry) -> String {
+    let entry = schema.resolve(entry);
+    if let Some(default) = schema.keyword(entry, "default") {
+        return write_entry(&schema.tree, default, &WriteOptions::compact());
+    }
+
+    match schema.keyword_str(entry, "type") {
+        Some("object") => "{}",
+        Some("array") => "[]",
+        Some("string") => "\"\"",
+        _ => "",
+    }
+    .to_owned()
+}
+
+/// The keys directly inside an object, including keys without a value.
+fn member_keys<'a>(tree: &'a Tree, object: &Entry) -> Vec<&'a Key> {
+    let children: Vec<Range> = tree
+        .object_entries(object)
+        .into_iter()
+        .filter(|(_, value)| is_container(value))
+        .map(|(_, value)| value.range)
+        .collect();
+
+    tree.keys
+        .iter()
+        .filter(|key| {
+            let range = tree.key_source_range(key);
+            object.range.start.idx < range.start.idx
+                && range.end.idx <= object.range.end.idx
+                && !children
+                    .iter()
+                    .any(|child| touches(*child, range.start.idx))
+        })
+        .collect()
+}
+
+fn property_items(
+    schema: &Schema,
+    object_schema: &Entry,
+    present: &[&str],
+    range: Range,
+    with_value: bool,
+) -> Vec<CompletionItem> {
+    let mut items: Vec<CompletionItem> = vec![];
+
+    alternatives(schema, object_schema)
+        .into_iter()
+        .filter_map(|entry| schema.keyword(entry, "properties"))
+        .flat_map(|properties| schema.tree.object_entries(properties))
+        .for_each(|(key, property)| {
+            if present.contains(&key.name.as_str()) || items.iter().any(|i| i.label == key.name) {
+                return;
+            }
+
+            let mut text = quote_string(&key.name);
+            if with_value {
+                text.push_str(": ");
+                text.push_str(&placeholder(schema, property));
+            }
+            items.push(CompletionItem {
+                label: key.name.clone(),
+                kind: CompletionKind::Property,
+                description: description(schema, property),
+                edit: TextEdit::new(range, &text),
+            });
+        });
+
+    items
+}
+
+fn value_items(schema: &Schema, value_schema: &Entry, range: Range) -> Vec<CompletionItem> {
+    let mut labels: Vec<String> = vec![];
+
+    alternatives(schema, value_schema)
+        .into_iter()
+        .for_each(|entry| {
+            let mut values: Vec<&Entry> = vec![];
+            ["enum", "examples"].iter().for_each(|name| {
+                if let Some(list) = schema.keyword(entry, name) {
+                    values.extend(schema.tree.array_entries(list));
+                }
+            });
+            ["const", "default"].iter().for_each(|name| {
+                values.extend(schema.keyword(entry, name));
+            });
+            labels.extend(
+                values
+                    .into_iter()
+                    .map(|value| write_entry(&schema.tree, value, &WriteOptions::compact())),
+            );
+
+            match schema.keyword_str(entry, "type") {
+                Some("boolean") => labels.extend(vec!["true".to_owned(), "false".to_owned()]),
+                Some("null") => labels.push("null".to_owned()),
+                _ => {}
+            }
+        });
+
+    let description = description(schema, value_schema);
+    let mut items: Vec<CompletionItem> = vec![];
+    labels.into_iter().for_each(|label| {
+        if !items.iter().any(|item| item.label == label) {
+            items.push(CompletionItem {
+                edit: TextEdit::new(range, &label),
+                label,
+                kind: CompletionKind::Value,
+                description: description.clone(),
+            });
+        }
+    });
+    items
+}
+
+fn complete_object(
+    tree: &Tree,
+    schema: &Schema,
+    object: &Entry,
+    object_schema: &Entry,
+    position: Position,
+) -> Vec<CompletionItem> {
+    let idx = position.idx;
+    let members = tree.object_entries(object);
+    let value_of = |key: &Key| {
+        members
+            .iter()
+            .find(|(member, _)| std::ptr::eq(*member, key))
+            .map(|(_, value)| *value)
+    };
+    let present = |except: Option<&Key>| -> Vec<&str> {
+        members
+            .iter()
+            .filter(|(key, _)| !except.is_some_and(|except| std::ptr::eq(*key, except)))
+            .map(|(key, _)| key.name.as_str())
+            .collect()
+    };
+    let insert = insert_range(tree, position);
+
+    let last = member_keys(tree, object)
+        .into_iter()
+        .rfind(|key| tree.key_source_range(key).start.idx < idx);
+
+    match last {
+        // The cursor is in a key, which is replaced.
+        Some(key) if idx <= tree.key_source_range(key).end.idx => property_items(
+            schema,
+            object_schema,
+            &present(Some(key)),
+            tree.key_source_range(key),
+            value_of(key).is_none(),
+        ),
+        Some(key) => {
+            let value_schema = schema.property_schema(object_schema, &key.name);
+            match (value_of(key), value_schema) {
+                (None, Some(value_schema)) => value_items(schema, value_schema, insert),
+                (Some(value), Some(value_schema))
+                    if touches(tree.entry_source_range(value), idx) =>
+                {
+                    value_items(schema, value_schema, tree.entry_source_range(value))
+                }
+                (Some(value), _) if touches(tree.entry_source_range(value), idx) => vec![],
+                (None, None) => vec![],
+                _ => property_items(schema, object_schema, &present(None), insert, true),
+            }
+        }
+        None => property_items(schema, object_schema, &present(None), insert, true),
+    }
+}
+
+fn complete_array(
+    tree: &Tree,
+    schema: &Schema,
+    array: &Entry,
+    array_schema: &Entry,
+    position: Position,
+) -> Vec<CompletionItem> {
+    let values = tree.array_entries(array);
+    let (idx, range) = match values
+        .iter()
+        .position(|value| touches(tree.entry_source_range(value), position.idx))
+    {
+        Some(idx) => (idx, tree.entry_source_range(values[idx])),
+        None => (
+            values
+                .iter()
+                .filter(|value| tree.entry_source_range(value).end.idx < position.idx)
+                .count(),
+            insert_range(tree, position),
+        ),
+    };
+
+    schema
+        .item_schema(array_schema, idx)
+        .map(|item_schema| value_items(schema, item_schema, range))
+        .unwrap_or_default()
+}
+
+/// Get completion candidates for the object or array around a position: the
+/// keys of the object which are not present yet, or the values allowed at the
+/// cursor. Works best with trees of `parse_json_tolerant`, as the text is
+/// usually incomplete while it is typed.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::{complete::complete, parse_json_tolerant, schema::Schema};
+/// # use json_position_parser::types::Position;
+/// let schema = Schema::parse("{ \"properties\": { \"host\": {}, \"port\": { \"description\": \"The port\" } } }").unwrap();
+/// let tree = parse_json_tolerant("{ \"host\": \"a\", ");
+/// let items = complete(&tree, &schema, Position::new(0, 15, 15));
+/// assert_eq!("port", items[0].label);
+/// assert_eq!("\"port\": ", items[0].edit.new_text);
+/// assert_eq!(Some("The port".to_owned()), items[0].description);
+/// ```
+pub fn complete(tree: &Tree, schema: &Schema, position: Position) -> Vec<CompletionItem> {
+    let container = match tree.container_at(position) {
+        Some(container) => container,
+        None => return vec![],
+    };
+    let container_schema = match schema.schema_at(&tree.path_of(container)) {
+        Some(container_schema) => container_schema,
+        None => return vec![],
+    };
+
+    match container.entry_type {
+        EntryType::JSONObject(_) => {
+            complete_object(tree, schema, container, container_schema, position)
+        }
+        _ => complete_array(tree, schema, container, container_schema, position),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{complete, CompletionKind};
+    use crate::parse_json_tolerant;
+    use crate::schema::Schema;
+    use crate::types::{Position, TextEdit};
+
+    const SCHEMA: &str = "{
+  \"properties\": {
+    \"name\": { \"type\": \"string\", \"description\": \"Name of the server\" },
+    \"mode\": { \"$ref\": \"#/$defs/mode\" },
+    \"tls\": { \"type\": \"boolean\" },
+    \"ports\": { \"type\": \"array\", \"items\": { \"enum\": [80, 443] } }
+  },
+  \"$defs\": { \"mode\": { \"enum\": [\"fast\", \"slow\"], \"default\": \"fast\" } }
+}";
+
+    fn labels(text: &str, idx: usize) -> Vec<String> {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let tree = parse_json_tolerant(text);
+        let position = crate::edit::position_at(text, idx);
+        complete(&tree, &schema, position)
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    #[test]
+    fn test_complete_keys() {
+        let text = "{\n  \"name\": \"a\",\n  \n}";
+        assert_eq!(vec!["mode", "tls", "ports"], labels(text, 19));
+
+        // A half-typed key is replaced, and keys inside nested values do not
+        // count as present.
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let text = "{ \"ports\": [], \"x\": { \"name\": 1 }, \"na";
+        let tree = parse_json_tolerant(text);
+        let items = complete(&tree, &schema, crate::edit::position_at(text, text.len()));
+        assert_eq!(3, items.len());
+        assert_eq!("name", items[0].label);
+        assert_eq!(CompletionKind::Property, items[0].kind);
+        assert_eq!(
+            "{ \"ports\": [], \"x\": { \"name\": 1 }, \"name\": \"\"",
+            TextEdit::apply(text, &[items[0].edit.clone()])
+        );
+
+        let text = "{ \"mode\": \"fast\" }";
+        assert!(labels(text, 0).is_empty());
+    }
+
+    #[test]
+    fn test_complete_values() {
+        assert_eq!(vec!["\"fast\"", "\"slow\""], labels("{ \"mode\": ", 10));
+        assert_eq!(vec!["true", "false"], labels("{ \"tls\": fa }", 9));
+        assert_eq!(vec!["80", "443"], labels("{ \"ports\": [80, ]", 16));
+
+        // A half-typed literal is replaced by the completion.
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let text = "{ \"tls\": fa";
+        let tree = parse_json_tolerant(text);
+        let items = complete(&tree, &schema, Position::new(0, 11, 11));
+        assert_eq!(
+            "{ \"tls\": true",
+            TextEdit::apply(text, &[items[0].edit.clone()])
+        );
+        let text = "{ \"ports\": [80, 4. ]";
+        let tree = parse_json_tolerant(text);
+        let items = complete(&tree, &schema, Position::new(0, 18, 18));
+        assert_eq!(
+            "{ \"ports\": [80, 443 ]",
+            TextEdit::apply(text, &[items[1].edit.clone()])
+        );
+
+        let text = "{ \"mode\": \"slow\", \"tls\": true }";
+        let tree = parse_json_tolerant(text);
+        let items = complete(&tree, &schema, Position::new(0, 12, 12));
+        assert_eq!(
+            "{ \"mode\": \"fast\", \"tls\": true }",
+            TextEdit::apply(text, &[items[0].edit.clone()])
+        );
+        assert_eq!(CompletionKind::Value, items[0].kind);
+
+        // An unclosed string is replaced up to the end of its line.
+        let text = "{ \"mode\": \"sl\n}";
+        let tree = parse_json_tolerant(text);
+        let items = complete(&tree, &schema, Position::new(0, 13, 13));
+        assert_eq!(
+            "{ \"mode\": \"slow\"\n}",
+            TextEdit::apply(text, &[items[1].edit.clone()])
+        );
+        let text = "{ \"mode\": \"";
+        let tree = parse_json_tolerant(text);
+        let items = complete(&tree, &schema, Position::new(0, 11, 11));
+        assert_eq!(
+            "{ \"mode\": \"fast\"",
+            TextEdit::apply(text, &[items[0].edit.clone()])
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use super::lex::{Lexer, TokenKind};
+use super::parser::tokenize::TokenType;
+use super::types::Range;
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SyntaxKind {
+    // Tokens
+    Whitespace,
+    Comment,
+    ObjectOpen,
+    ObjectClose,
+    ArrayOpen,
+    ArrayClose,
+    Comma,
+    Colon,
+    String,
+    Number,
+    Bool,
+    Null,
+    Invalid,
+    // Nodes
+    Document,
+    Object,
+    Array,
+    /// A key, the colon and the value of an object member.
+    Member,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        self == SyntaxKind::Whitespace || self == SyntaxKind::Comment
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    pub kind: SyntaxKind,
+    /// Range of the token including the quotes of strings.
+    pub range: Range,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub range: Range,
+    pub children: Vec<SyntaxElement>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn range(&self) -> Range {
+        match self {
+            SyntaxElement::Node(node) => node.range,
+            SyntaxElement::Token(token) => token.range,
+        }
+    }
+}
+
+impl SyntaxNode {
+    /// All tokens of the node in source order, trivia included.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        self.children
+            .iter()
+            .flat_map(|child| match child {
+                SyntaxElement::Node(node) => node.tokens(),
+                SyntaxElement::Token(token) => vec![token],
+            })
+            .collect()
+    }
+
+    /// Get the token covering the byte offset `idx`, e.g. the `range.start.idx`
+    /// of an `Entry`. Changing its text changes the written document.
+    pub fn token_at_mut(&mut self, idx: usize) -> Option<&mut SyntaxToken> {
+        self.children.iter_mut().find_map(|child| match child {
+            SyntaxElement::Node(node) if contains(&node.range, idx) => node.token_at_mut(idx),
+            SyntaxElement::Token(token) if contains(&token.range, idx) => Some(token),
+            _ => None,
+        })
+    }
+}
+
+fn contains(range: &Range, idx: usize) -> bool {
+    range.start.idx <= idx && idx < range.end.idx
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.tokens()
+            .iter()
+            .try_for_each(|token| f.write_str(&token.text))
+    }
+}
+
+fn syntax_kind(kind: TokenKind) -> SyntaxKind {
+    match kind {
+        TokenKind::Whitespace => SyntaxKind::Whitespace,
+        TokenKind::Comment => SyntaxKind::Comment,
+        TokenKind::ObjectOpen => SyntaxKind::ObjectOpen,
+        TokenKind::ObjectClose => SyntaxKind::ObjectClose,
+        TokenKind::ArrayOpen => SyntaxKind::ArrayOpen,
+        TokenKind::ArrayClose => SyntaxKind::ArrayClose,
+        TokenKind::Comma => SyntaxKind::Comma,
+        TokenKind::Colon => SyntaxKind::Colon,
+        TokenKind::String => SyntaxKind::String,
+        TokenKind::Number => SyntaxKind::Number,
+        TokenKind::Bool => SyntaxKind::Bool,
+        TokenKind::Null => SyntaxKind::Null,
+        TokenKind::Error => SyntaxKind::Invalid,
+    }
+}
+
+struct Builder {
+    tokens: std::iter::Peekable<std::vec::IntoIter<SyntaxToken>>,
+}
+
+impl Builder {
+    fn peek_kind(&mut self) -> Option<SyntaxKind> {
+        self.tokens.peek().map(|token| token.kind)
+    }
+
+    fn bump(&mut self, children: &mut Vec<SyntaxElement>) {
+        if let Some(token) = self.tokens.next() {
+            children.push(SyntaxElement::Token(token));
+        }
+    }
+
+    fn trivia(&mut self, children: &mut Vec<SyntaxElement>) {
+        while matches!(self.peek_kind(), Some(kind) if kind.is_trivia()) {
+            self.bump(children);
+        }
+    }
+
+    fn node(kind: SyntaxKind, children: Vec<SyntaxElement>) -> SyntaxNode {
+        let range = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => Range::new(first.range().start, last.range().end),
+            _ => Range::default(),
+        };
+
+        SyntaxNode {
+            kind,
+            range,
+            children,
+        }
+    }
+
+    fn value(&mut self) -> Option<SyntaxElement> {
+        match self.peek_kind()? {
+            SyntaxKind::ObjectOpen => Some(SyntaxElement::Node(
+                self.container(SyntaxKind::Object, SyntaxKind::ObjectClose),
+            )),
+            SyntaxKind::ArrayOpen => Some(SyntaxElement::Node(
+                self.container(SyntaxKind::Array, SyntaxKind::ArrayClose),
+            )),
+            _ => self.tokens.next().map(SyntaxElement::Token),
+        }
+    }
+
+    fn container(&mut self, kind: SyntaxKind, close: SyntaxKind) -> SyntaxNode {
+        let mut children = vec![];
+        self.bump(&mut children);
+
+        loop {
+            self.trivia(&mut children);
+            match self.peek_kind() {
+                None => break,
+                Some(k) if k == close => {
+                    self.bump(&mut children);
+                    break;
+                }
+                Some(SyntaxKind::Comma) => self.bump(&mut children),
+                Some(SyntaxKind::String) if kind == SyntaxKind::Object => {
+                    children.push(SyntaxElement::Node(self.member()))
+                }
+                _ => children.extend(self.value()),
+            }
+        }
+
+        Builder::node(kind, children)
+    }
+
+    fn member(&mut self) -> SyntaxNode {
+        let mut children = vec![];
+        self.bump(&mut children);
+
+        self.trivia(&mut children);
+        if self.peek_kind() == Some(SyntaxKind::Colon) {
+            self.bump(&mut children);
+            self.trivia(&mut children);
+            children.extend(self.value());
+        }
+
+        Builder::node(SyntaxKind::Member, children)
+    }
+
+    fn document(&mut self) -> SyntaxNode {
+        let mut children = vec![];
+
+        loop {
+            self.trivia(&mut children);
+            match self.value() {
+                Some(value) => children.push(value),
+                None => break,
+            }
+        }
+
+        Builder::node(SyntaxKind::Document, children)
+    }
+}
+
+/// Build the concrete syntax tree of `text` from its tokens.
+pub(crate) fn build(text: &str, tokens: Vec<TokenType>) -> SyntaxNode {
+    let tokens: Vec<SyntaxToken> = Lexer::with_tokens(text, tokens)
+        .map(|token| SyntaxToken {
+            kind: syntax_kind(token.kind),
+            range: token.range,
+            text: token.text.to_owned(),
+        })
+        .collect();
+    let mut builder = Builder {
+        tokens: tokens.into_iter().peekable(),
+    };
+    builder.document()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyntaxKind;
+    use crate::{parse_cst, parse_json, tree::PathType};
+
+    #[test]
+    fn test_round_trip() {
+        let texts = [
+            "{}",
+            "  // leading\n\n{ \"a\" :1 ,\"b\":[ true,null ,\t{}], // trailing\r\n  \"c\\\"\": -1.5e3 }\n\n// end",
+            "{\n    \"nested\": {\n        \"deep\": [ [ ], { } ]\n    }\n}\n",
+            "{ \"\u{e9}\u{1f600}\": \"\u{fc}\" }",
+        ];
+
+        texts.iter().for_each(|text| {
+            let cst = parse_cst(text).unwrap();
+            assert_eq!(*text, cst.to_string());
+        });
+    }
+
+    #[test]
+    fn test_edit_value() {
+        let text = "{\n  // port of the server\n  \"port\": 8080, \"host\": \"a\"\n}\n";
+        let tree = parse_json(text).unwrap();
+        let port = tree.value_at(&[PathType::Object("port")])[0];
+
+        let mut cst = parse_cst(text).unwrap();
+        let token = cst.token_at_mut(port.range.start.idx).unwrap();
+        assert_eq!(SyntaxKind::Number, token.kind);
+        token.text = "9090".to_owned();
+
+        assert_eq!(
+            "{\n  // port of the server\n  \"port\": 9090, \"host\": \"a\"\n}\n",
+            cst.to_string()
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use super::parse_json;
+use super::tree::{Entry, EntryType, JsonPath, Key, Tree};
+use super::types::{ParseError, Range};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use std::{error, fmt};
+
+/// A deserialization error with the range and path of the entry causing it.
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub message: String,
+    pub range: Option<Range>,
+    pub path: Option<JsonPath>,
+}
+
+impl Error {
+    /// Locate the error at an entry, unless it is located already.
+    fn at(mut self, range: Range, path: &JsonPath) -> Error {
+        if self.range.is_none() {
+            self.range = Some(range);
+            self.path = Some(path.clone());
+        }
+        self
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error {
+            message: msg.to_string(),
+            range: None,
+            path: None,
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Error {
+        let range = match error {
+            ParseError::UnexpectedCharacter(range)
+            | ParseError::UnterminatedLiteral(range)
+            | ParseError::TrailingContent(range) => Some(range),
+            _ => None,
+        };
+
+        Error {
+            message: error.to_string(),
+            range,
+            path: None,
+        }
+    }
+}
+
+// Formats the error like `14:9: expected u16 at $.server.port`.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(range) = self.range {
+            write!(f, "{}: ", range.start)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(path) = &self.path {
+            write!(f, " at {}", path)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Deserializes an entry of a tree and its children.
+pub struct EntryDeserializer<'de> {
+    tree: &'de Tree,
+    entry: &'de Entry,
+    path: JsonPath,
+}
+
+impl<'de> EntryDeserializer<'de> {
+    pub fn new(tree: &'de Tree, entry: &'de Entry) -> EntryDeserializer<'de> {
+        EntryDeserializer {
+            tree,
+            entry,
+            path: tree.path_of(entry),
+        }
+    }
+
+    fn child(&self, entry: &'de Entry, path: JsonPath) -> EntryDeserializer<'de> {
+        EntryDeserializer {
+            tree: self.tree,
+            entry,
+            path,
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for EntryDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let (range, path) = (self.entry.source_range(), self.path.clone());
+        match &self.entry.entry_type {
+            EntryType::JSONObject(_) => visitor.visit_map(MapAccess {
+                members: self.tree.object_entries(self.entry).into_iter(),
+                value: None,
+                parent: &self,
+            }),
+            EntryType::JSONArray(_) => visitor.visit_seq(SeqAccess {
+                values: self.tree.array_entries(self.entry).into_iter().enumerate(),
+                parent: &self,
+            }),
+            EntryType::String(value) => visitor.visit_borrowed_str(value),
+            EntryType::Int(value) => visitor.visit_i64(*value),
+            EntryType::Float(value) => visitor.visit_f64(*value),
+            EntryType::Bool(value) => visitor.visit_bool(*value),
+            EntryType::Null => visitor.visit_unit(),
+        }
+        .map_err(|e| e.at(range, &path))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let (range, path) = (self.entry.source_range(), self.path.clone());
+        match self.entry.entry_type {
+            EntryType::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+        .map_err(|e| e.at(range, &path))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let (range, path) = (self.entry.source_range(), self.path.clone());
+        match &self.entry.entry_type {
+            EntryType::String(value) => visitor.visit_enum(value.as_str().into_deserializer()),
+            EntryType::JSONObject(_) => match self.tree.object_entries(self.entry)[..] {
+                [(key, value)] => visitor.visit_enum(EnumAccess {
+                    key,
+                    value: self.child(value, self.path.with_key(&key.name)),
+                }),
+                _ => Err(de::Error::custom("expected an object with a single key")),
+            },
+            _ => Err(de::Error::custom("expected a string or an object")),
+        }
+        .map_err(|e| e.at(range, &path))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct MapAccess<'a, 'de> {
+    members: std::vec::IntoIter<(&'de Key, &'de Entry)>,
+    value: Option<(&'de Key, &'de Entry)>,
+    parent: &'a EntryDeserializer<'de>,
+}
+
+impl<'a, 'de> de::MapAccess<'de> for MapAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.members.next() {
+            Some((key, value)) => {
+                self.value = Some((key, value));
+                let path = self.parent.path.with_key(&key.name);
+                seed.deserialize(de::value::BorrowedStrDeserializer::new(&key.name))
+                    .map(Some)
+                    .map_err(|e: Error| e.at(key.source_range(), &path))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        match self.value.take() {
+            Some((key, value)) => seed.deserialize(
+                self.parent
+                    .child(value, self.parent.path.with_key(&key.name)),
+            ),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+}
+
+struct SeqAccess<'a, 'de> {
+    values: std::iter::Enumerate<std::vec::IntoIter<&'de Entry>>,
+    parent: &'a EntryDeserializer<'de>,
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.values.next() {
+            Some((idx, value)) => seed
+                .deserialize(self.parent.child(value, self.parent.path.with_index(idx)))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct EnumAccess<'de> {
+    key: &'de Key,
+    value: EntryDeserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = Error;
+    type Variant = EntryDeserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let (range, path) = (self.key.source_range(), self.value.path.clone());
+        seed.deserialize(de::value::BorrowedStrDeserializer::new(&self.key.name))
+            .map(|variant| (variant, self.value))
+            .map_err(|e: Error| e.at(range, &path))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for EntryDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Deserialize an entry of a tree.
+pub fn from_entry<'de, T: de::Deserialize<'de>>(tree: &'de Tree, entry: &'de Entry) -> Result<T> {
+    T::deserialize(EntryDeserializer::new(tree, entry))
+}
+
+/// Deserialize a whole tree.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::{de::from_tree, parse_json};
+/// #[derive(serde::Deserialize)]
+/// struct Server {
+///     port: u16,
+/// }
+///
+/// let tree = parse_json("{\n  \"port\": \"80\"\n}").unwrap();
+/// let error = from_tree::<Server>(&tree).err().unwrap();
+/// assert_eq!(
+///     "2:11: invalid type: string \"80\", expected u16 at $.port",
+///     error.to_string()
+/// );
+/// ```
+pub fn from_tree<'de, T: de::Deserialize<'de>>(tree: &'de Tree) -> Result<T> {
+    match tree.root() {
+        Some(root) => from_entry(tree, root),
+        None => Err(de::Error::custom("empty tree")),
+    }
+}
+
+/// Parse and deserialize a json text.
+pub fn from_str<T: de::DeserializeOwned>(text: &str) -> Result<T> {
+    let tree = parse_json(text)?;
+    from_tree(&tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_str, from_tree};
+    use crate::parse_json;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Mode {
+        Fast,
+        Limited(u8),
+        Custom { level: i32 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+        tags: Vec<String>,
+        weight: Option<f64>,
+        mode: Mode,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        servers: Vec<Server>,
+        env: HashMap<String, String>,
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let text = "{ \"servers\": [\n  { \"host\": \"a\", \"port\": 80, \"tags\": [\"x\"], \"weight\": 1.5, \"mode\": \"Fast\" },\n  { \"host\": \"b\", \"port\": 81, \"tags\": [], \"weight\": null, \"mode\": { \"Custom\": { \"level\": -3 } } }\n], \"env\": { \"a\": \"b\" } }";
+        let tree = parse_json(text).unwrap();
+        let config: Config = from_tree(&tree).unwrap();
+
+        assert_eq!(2, config.servers.len());
+        assert_eq!(Mode::Fast, config.servers[0].mode);
+        assert_eq!(Some(1.5), config.servers[0].weight);
+        assert_eq!(Mode::Custom { level: -3 }, config.servers[1].mode);
+        assert_eq!(None, config.servers[1].weight);
+        assert_eq!("b", config.env["a"]);
+        assert_eq!(Mode::Limited(4), from_str("{ \"Limited\": 4 }").unwrap());
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let text = "{ \"servers\": [\n  { \"host\": \"a\", \"port\": 70000, \"tags\": [], \"mode\": \"Fast\" }\n], \"env\": {} }";
+        let error = from_str::<Config>(text).err().unwrap();
+        assert_eq!(
+            "2:26: invalid value: integer `70000`, expected u16 at $.servers[0].port",
+            error.to_string()
+        );
+
+        let text = "{ \"servers\": [\n  { \"host\": \"a\", \"tags\": [], \"mode\": \"Fast\" }\n], \"env\": {} }";
+        let error = from_str::<Config>(text).err().unwrap();
+        assert_eq!(
+            "2:3: missing field `port` at $.servers[0]",
+            error.to_string()
+        );
+
+        let error = from_str::<Config>("{ \"servers\": [] ").err().unwrap();
+        assert_eq!("Missing object brace", error.to_string());
+    }
+}
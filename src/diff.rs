@@ -0,0 +1,365 @@
+use super::tree::{entries_equal, Entry, EntryType, JsonPath, Key, Tree};
+use super::types::Range;
+use super::writer::{write_entry, WriteOptions};
+use std::fmt::Write;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// A difference between two trees. The path is the one in the new tree, or in
+/// the old tree for removed values. For added values, `old_range` is the range
+/// of the object or array they were added to, and for removed values,
+/// `new_range` is the range of the object or array they were removed from.
+/// `old_entry` and `new_entry` are the indices in `entries` of the values at
+/// these ranges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub path: JsonPath,
+    pub old_range: Option<Range>,
+    pub new_range: Option<Range>,
+    pub old_entry: Option<usize>,
+    pub new_entry: Option<usize>,
+}
+
+/// How array values of the old and new tree are paired up.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ArrayMatching {
+    /// Values at the same index are compared.
+    #[default]
+    ByIndex,
+    /// Objects with the same value at this key are compared, wherever they
+    /// are in the arrays. Values without the key are compared by index.
+    ByKey(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    pub arrays: ArrayMatching,
+}
+
+/// The keys and value indices of an object entry in source order.
+fn members<'a>(tree: &'a Tree, entry: &Entry) -> Vec<(&'a Key, usize)> {
+    let mut members: Vec<(usize, usize)> = match &entry.entry_type {
+        EntryType::JSONObject(hash) => hash.values().cloned().collect(),
+        _ => vec![],
+    };
+    members.sort_unstable();
+
+    members
+        .into_iter()
+        .filter_map(|(key, value)| Some((tree.keys.get(key)?, value)))
+        .collect()
+}
+
+/// The value indices of an array entry.
+fn elements(entry: &Entry) -> &[usize] {
+    match &entry.entry_type {
+        EntryType::JSONArray(array) => array,
+        _ => &[],
+    }
+}
+
+struct Differ<'a> {
+    old: &'a Tree,
+    new: &'a Tree,
+    options: &'a DiffOptions,
+    changes: Vec<Change>,
+}
+
+impl<'a> Differ<'a> {
+    /// Record a change between the entries at `old` and `new`.
+    fn push(&mut self, kind: ChangeKind, path: JsonPath, old: usize, new: usize) {
+        self.changes.push(Change {
+            kind,
+            path,
+            old_range: Some(self.old.entries[old].source_range()),
+            new_range: Some(self.new.entries[new].source_range()),
+            old_entry: Some(old),
+            new_entry: Some(new),
+        });
+    }
+
+    fn id_of(&self, tree: &Tree, entry: &Entry) -> Option<String> {
+        let key = match &self.options.arrays {
+            ArrayMatching::ByKey(key) => key,
+            ArrayMatching::ByIndex => return None,
+        };
+        match &entry.entry_type {
+            EntryType::JSONObject(hash) => hash
+                .get(key)
+                .and_then(|(_, value)| tree.entries.get(*value))
+                .map(|value| write_entry(tree, value, &WriteOptions::compact())),
+            _ => None,
+        }
+    }
+
+    fn diff_objects(&mut self, old: usize, new: usize, path: &JsonPath) {
+        let old_members = members(self.old, &self.old.entries[old]);
+        let new_members = members(self.new, &self.new.entries[new]);
+
+        old_members.iter().for_each(|(key, old_value)| {
+            let path = path.with_key(&key.name);
+            match new_members.iter().find(|(other, _)| other.name == key.name) {
+                Some((_, new_value)) => self.diff_entries(*old_value, *new_value, &path),
+                None => self.push(ChangeKind::Removed, path, *old_value, new),
+            }
+        });
+        new_members
+            .iter()
+            .filter(|(key, _)| !old_members.iter().any(|(other, _)| other.name == key.name))
+            .for_each(|(key, new_value)| {
+                self.push(ChangeKind::Added, path.with_key(&key.name), old, *new_value)
+            });
+    }
+
+    fn diff_arrays(&mut self, old: usize, new: usize, path: &JsonPath) {
+        let old_values = elements(&self.old.entries[old]);
+        let new_values = elements(&self.new.entries[new]);
+        let old_ids: Vec<Option<String>> = old_values
+            .iter()
+            .map(|value| self.id_of(self.old, &self.old.entries[*value]))
+            .collect();
+        let new_ids: Vec<Option<String>> = new_values
+            .iter()
+            .map(|value| self.id_of(self.new, &self.new.entries[*value]))
+            .collect();
+
+        // The index of the matching old value for every new value. An old
+        // value is matched at most once, even if its id repeats.
+        let mut used = vec![false; old_values.len()];
+        let pairs: Vec<Option<usize>> = new_ids
+            .iter()
+            .enumerate()
+            .map(|(idx, id)| {
+                let pair = match id {
+                    Some(_) => (0..old_ids.len()).find(|old| !used[*old] && old_ids[*old] == *id),
+                    None => Some(idx).filter(|idx| {
+                        *idx < old_ids.len() && old_ids[*idx].is_none() && !used[*idx]
+                    }),
+                };
+                if let Some(old) = pair {
+                    used[old] = true;
+                }
+                pair
+            })
+            .collect();
+
+        (0..old_values.len())
+            .filter(|idx| !used[*idx])
+            .for_each(|idx| {
+                self.push(
+                    ChangeKind::Removed,
+                    path.with_index(idx),
+                    old_values[idx],
+                    new,
+                )
+            });
+        pairs.iter().enumerate().for_each(|(idx, pair)| match pair {
+            Some(old_idx) => {
+                self.diff_entries(old_values[*old_idx], new_values[idx], &path.with_index(idx))
+            }
+            None => self.push(
+                ChangeKind::Added,
+                path.with_index(idx),
+                old,
+                new_values[idx],
+            ),
+        });
+    }
+
+    fn diff_entries(&mut self, old: usize, new: usize, path: &JsonPath) {
+        let (old_entry, new_entry) = (&self.old.entries[old], &self.new.entries[new]);
+        match (&old_entry.entry_type, &new_entry.entry_type) {
+            (EntryType::JSONObject(_), EntryType::JSONObject(_)) => {
+                self.diff_objects(old, new, path)
+            }
+            (EntryType::JSONArray(_), EntryType::JSONArray(_)) => self.diff_arrays(old, new, path),
+            _ if entries_equal(self.old, old_entry, self.new, new_entry) => {}
+            _ => self.push(ChangeKind::Modified, path.clone(), old, new),
+        }
+    }
+}
+
+/// Get the differences between two trees, comparing arrays by index.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::{diff::{diff, ChangeKind}, parse_json};
+/// let old = parse_json("{\n  \"port\": 80\n}").unwrap();
+/// let new = parse_json("{\n  \"debug\": true,\n  \"port\": 8080\n}").unwrap();
+/// let changes = diff(&old, &new);
+/// assert_eq!(ChangeKind::Modified, changes[0].kind);
+/// assert_eq!("$.port", changes[0].path.to_string());
+/// assert_eq!(2, changes[0].new_range.unwrap().start.line);
+/// assert_eq!(ChangeKind::Added, changes[1].kind);
+/// ```
+pub fn diff(old: &Tree, new: &Tree) -> Vec<Change> {
+    diff_with(old, new, &DiffOptions::default())
+}
+
+/// Get the differences between two trees.
+pub fn diff_with(old: &Tree, new: &Tree, options: &DiffOptions) -> Vec<Change> {
+    let mut differ = Differ {
+        old,
+        new,
+        options,
+        changes: vec![],
+    };
+    // The root is the last entry.
+    if let (Some(old_root), Some(new_root)) = (
+        old.entries.len().checked_sub(1),
+        new.entries.len().checked_sub(1),
+    ) {
+        differ.diff_entries(old_root, new_root, &JsonPath::default());
+    }
+    differ.changes
+}
+
+fn preview(tree: &Tree, entry: Option<usize>) -> String {
+    let text = entry
+        .and_then(|entry| tree.entries.get(entry))
+        .map(|entry| write_entry(tree, entry, &WriteOptions::compact()))
+        .unwrap_or_default();
+
+    match text.char_indices().nth(60) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text,
+    }
+}
+
+/// Render changes one per line with their line numbers, e.g.
+/// `~ $.port (line 3 -> 4): 80 -> 8080`.
+pub fn render(old: &Tree, new: &Tree, changes: &[Change]) -> String {
+    let line = |range: Option<Range>| range.map_or(0, |range| range.start.line + 1);
+
+    changes.iter().fold(String::new(), |mut out, change| {
+        let _ = match change.kind {
+            ChangeKind::Added => writeln!(
+                out,
+                "+ {} (line {}): {}",
+                change.path,
+                line(change.new_range),
+                preview(new, change.new_entry)
+            ),
+            ChangeKind::Removed => writeln!(
+                out,
+                "- {} (line {}): {}",
+                change.path,
+                line(change.old_range),
+                preview(old, change.old_entry)
+            ),
+            ChangeKind::Modified => writeln!(
+                out,
+                "~ {} (line {} -> {}): {} -> {}",
+                change.path,
+                line(change.old_range),
+                line(change.new_range),
+                preview(old, change.old_entry),
+                preview(new, change.new_entry)
+            ),
+        };
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, diff_with, render, ArrayMatching, ChangeKind, DiffOptions};
+    use crate::parse_json;
+
+    #[test]
+    fn test_diff() {
+        let old = parse_json(
+            "{\n  \"a\": { \"b\": 1, \"c\": [1, 2, 3] },\n  \"d\": \"x\",\n  \"e\": null\n}",
+        )
+        .unwrap();
+        let new =
+            parse_json("{\n  \"a\": { \"b\": 1, \"c\": [1, 5] },\n  \"d\": 1,\n  \"f\": {}\n}")
+                .unwrap();
+        let changes = diff(&old, &new);
+
+        let kinds: Vec<(ChangeKind, String)> = changes
+            .iter()
+            .map(|c| (c.kind, c.path.to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                (ChangeKind::Removed, "$.a.c[2]".to_owned()),
+                (ChangeKind::Modified, "$.a.c[1]".to_owned()),
+                (ChangeKind::Modified, "$.d".to_owned()),
+                (ChangeKind::Removed, "$.e".to_owned()),
+                (ChangeKind::Added, "$.f".to_owned()),
+            ],
+            kinds
+        );
+        assert_eq!(
+            "- $.a.c[2] (line 2): 3\n~ $.a.c[1] (line 2 -> 2): 2 -> 5\n~ $.d (line 3 -> 3): \"x\" -> 1\n- $.e (line 4): null\n+ $.f (line 4): {}\n",
+            render(&old, &new, &changes)
+        );
+        assert_eq!(Some(new.root().unwrap().range), changes[3].new_range);
+        assert_eq!(Some(new.entries.len() - 1), changes[3].new_entry);
+
+        // Numbers are compared by value, like `entries_equal` does.
+        let old = parse_json("{ \"a\": 1, \"b\": [2.0] }").unwrap();
+        let new = parse_json("{ \"a\": 1.0, \"b\": [2] }").unwrap();
+        assert!(diff(&old, &new).is_empty());
+
+        // Integers are compared exactly, even past the precision of f64.
+        let old = parse_json("{ \"a\": 9007199254740992 }").unwrap();
+        let new = parse_json("{ \"a\": 9007199254740993 }").unwrap();
+        let changes = diff(&old, &new);
+        assert_eq!(1, changes.len());
+        assert_eq!(ChangeKind::Modified, changes[0].kind);
+    }
+
+    #[test]
+    fn test_diff_by_key() {
+        let old =
+            parse_json("{ \"s\": [{ \"id\": 1, \"v\": 1 }, { \"id\": 2, \"v\": 2 }] }").unwrap();
+        let new = parse_json(
+            "{ \"s\": [{ \"id\": 2, \"v\": 3 }, { \"id\": 1, \"v\": 1 }, { \"id\": 3 }] }",
+        )
+        .unwrap();
+
+        assert_eq!(5, diff(&old, &new).len());
+
+        let options = DiffOptions {
+            arrays: ArrayMatching::ByKey("id".to_owned()),
+        };
+        let changes = diff_with(&old, &new, &options);
+        assert_eq!(2, changes.len());
+        assert_eq!(ChangeKind::Modified, changes[0].kind);
+        assert_eq!("$.s[0].v", changes[0].path.to_string());
+        assert_eq!(Some(45), changes[0].old_range.map(|r| r.start.idx));
+        assert_eq!(ChangeKind::Added, changes[1].kind);
+        assert_eq!("$.s[2]", changes[1].path.to_string());
+
+        // An old value is paired once, further values with its id are added.
+        let old = parse_json("{ \"s\": [{ \"id\": 1 }] }").unwrap();
+        let new = parse_json("{ \"s\": [{ \"id\": 1 }, { \"id\": 1, \"v\": 2 }] }").unwrap();
+        let changes: Vec<(ChangeKind, String)> = diff_with(&old, &new, &options)
+            .iter()
+            .map(|c| (c.kind, c.path.to_string()))
+            .collect();
+        assert_eq!(vec![(ChangeKind::Added, "$.s[1]".to_owned())], changes);
+
+        let old = parse_json("{ \"s\": [{ \"id\": 1 }, { \"id\": 1, \"v\": 2 }] }").unwrap();
+        let new = parse_json("{ \"s\": [{ \"id\": 1, \"v\": 2 }, { \"id\": 1 }] }").unwrap();
+        let changes: Vec<(ChangeKind, String)> = diff_with(&old, &new, &options)
+            .iter()
+            .map(|c| (c.kind, c.path.to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                (ChangeKind::Added, "$.s[0].v".to_owned()),
+                (ChangeKind::Removed, "$.s[1].v".to_owned()),
+            ],
+            changes
+        );
+    }
+}
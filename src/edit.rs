@@ -0,0 +1,633 @@
+use super::parser::parse;
+use super::parser::tokenize::{self, TokenType};
+use super::tree::{Entry, EntryType, JsonPath, PathQuery, PathType, Tree};
+use super::types::{ParseError, ParseResult, Position, Range, TextEdit};
+use super::writer::quote_string;
+
+/// Get the position of the byte offset `idx` in `text`.
+pub fn position_at(text: &str, idx: usize) -> Position {
+    let before = &text[..idx];
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    Position::new(
+        before.matches('\n').count(),
+        before[line_start..].chars().count(),
+        idx,
+    )
+}
+
+fn line_start(text: &str, idx: usize) -> usize {
+    text[..idx].rfind('\n').map_or(0, |pos| pos + 1)
+}
+
+fn line_end(text: &str, idx: usize) -> usize {
+    text[idx..].find('\n').map_or(text.len(), |pos| idx + pos)
+}
+
+/// Get the whitespace at the start of the line containing `idx`.
+pub fn indentation_at(text: &str, idx: usize) -> &str {
+    let line = &text[line_start(text, idx)..line_end(text, idx)];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Detect the indentation unit of a text. Defaults to two spaces.
+pub fn detect_indent(text: &str) -> String {
+    let indents: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .filter(|indent| !indent.is_empty())
+        .collect();
+
+    match indents.first() {
+        Some(indent) if indent.starts_with('\t') => "\t".to_owned(),
+        Some(_) => {
+            let width = indents.iter().map(|indent| indent.len()).min().unwrap_or(2);
+            " ".repeat(width)
+        }
+        None => "  ".to_owned(),
+    }
+}
+
+/// Indent every line but the first of a json value.
+fn reindent(value: &str, indent: &str) -> String {
+    value
+        .trim()
+        .lines()
+        .collect::<Vec<&str>>()
+        .join(&format!("\n{}", indent))
+}
+
+/// Parse the text of a single value given for an edit. Comments are rejected
+/// as they could hide the rest of the line the value is written to. The value
+/// is at the pointer `/` of the tree.
+pub(crate) fn parse_value(value: &str) -> ParseResult<Tree> {
+    let tokens = tokenize::tokenize(value)?;
+    if let Some(comment) = tokens
+        .iter()
+        .find(|token| matches!(token, TokenType::Comment(_)))
+    {
+        return Err(ParseError::UnexpectedCharacter(comment.range()));
+    }
+    parse::parse_json_value(tokens)
+}
+
+fn validate(value: &str) -> ParseResult<()> {
+    parse_value(value).map(|_| ())
+}
+
+fn range_at(text: &str, start: usize, end: usize) -> Range {
+    Range::new(position_at(text, start), position_at(text, end))
+}
+
+/// Join overlapping removals and drop edits contained in others.
+fn merge_removals(mut edits: Vec<TextEdit>) -> Vec<TextEdit> {
+    edits.sort_by_key(|edit| (edit.range.start.idx, edit.range.end.idx));
+    edits
+        .into_iter()
+        .fold(vec![], |mut merged: Vec<TextEdit>, edit| {
+            match merged.last_mut() {
+                Some(last) if edit.range.start.idx < last.range.end.idx => {
+                    if edit.range.end.idx > last.range.end.idx {
+                        last.range.end = edit.range.end;
+                    }
+                }
+                _ => merged.push(edit),
+            }
+            merged
+        })
+}
+
+/// A key which is not renamed, because its object already has the new name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenameConflict {
+    /// The path of the member to rename.
+    pub path: JsonPath,
+    /// The range of the key to rename, including quotes.
+    pub range: Range,
+    /// The range of the key which already has the new name.
+    pub existing: Range,
+}
+
+/// The edits renaming a key, and the keys left as they are.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rename {
+    pub edits: Vec<TextEdit>,
+    pub conflicts: Vec<RenameConflict>,
+}
+
+impl Tree {
+    /// The range of an object member or array value, including the key.
+    fn element_range(&self, entry: &Entry) -> Range {
+        let range = entry.source_range();
+        match entry.key.and_then(|key| self.keys.get(key)) {
+            Some(key) => Range::new(key.source_range().start, range.end),
+            None => range,
+        }
+    }
+
+    /// Drop matches that are inside of other matches.
+    fn outermost<'a>(&self, entries: Vec<&'a Entry>) -> Vec<&'a Entry> {
+        entries
+            .iter()
+            .filter(|entry| {
+                !entries.iter().any(|other| {
+                    !std::ptr::eq(*other, **entry)
+                        && other.range.start.idx <= entry.range.start.idx
+                        && entry.range.end.idx <= other.range.end.idx
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Get the edits replacing the values at `path` with `new_json`.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::{parse_json, tree::PathType, types::TextEdit};
+    /// let text = "{\n  // the port\n  \"port\": 8080\n}";
+    /// let tree = parse_json(text).unwrap();
+    /// let edits = tree
+    ///     .set_value_edits(text, &[PathType::Object("port")], "9090")
+    ///     .unwrap();
+    /// assert_eq!("{\n  // the port\n  \"port\": 9090\n}", TextEdit::apply(text, &edits));
+    /// ```
+    pub fn set_value_edits(
+        &self,
+        text: &str,
+        path: &[PathType],
+        new_json: &str,
+    ) -> ParseResult<Vec<TextEdit>> {
+        validate(new_json)?;
+        let entries = self.value_at(path);
+        if entries.is_empty() {
+            return Err(ParseError::PathNotFound);
+        }
+
+        Ok(self
+            .outermost(entries)
+            .iter()
+            .map(|entry| {
+                let range = entry.source_range();
+                let indent = indentation_at(text, range.start.idx);
+                TextEdit::new(range, &reindent(new_json, indent))
+            })
+            .collect())
+    }
+
+    /// Get the edits adding `key` with the value `new_json` to the objects at
+    /// `path`. An existing value of the key is replaced.
+    pub fn insert_key_edits(
+        &self,
+        text: &str,
+        path: &[PathType],
+        key: &str,
+        new_json: &str,
+    ) -> ParseResult<Vec<TextEdit>> {
+        validate(new_json)?;
+        let objects = self.value_at(path);
+        if objects.is_empty() {
+            return Err(ParseError::PathNotFound);
+        }
+
+        let mut edits = vec![];
+        for object in objects {
+            if !matches!(object.entry_type, EntryType::JSONObject(_)) {
+                return Err(ParseError::InvalidType);
+            }
+
+            let members = self.object_entries(object);
+            if let Some((_, existing)) = members.iter().find(|(k, _)| k.name == key) {
+                let range = existing.source_range();
+                let indent = indentation_at(text, range.start.idx);
+                edits.push(TextEdit::new(range, &reindent(new_json, indent)));
+                continue;
+            }
+
+            let open_end = object.range.start.idx + 1;
+            let close_start = object.range.end.idx - 1;
+            match members.last() {
+                None if object.range.start.line == object.range.end.line => {
+                    let member = format!("{}: {}", quote_string(key), new_json.trim());
+                    edits.push(TextEdit::new(
+                        range_at(text, open_end, close_start),
+                        &format!(" {} ", member),
+                    ));
+                }
+                None => {
+                    let indent = format!(
+                        "{}{}",
+                        indentation_at(text, object.range.start.idx),
+                        detect_indent(text)
+                    );
+                    let member = format!("{}: {}", quote_string(key), reindent(new_json, &indent));
+                    edits.push(TextEdit::new(
+                        range_at(text, open_end, open_end),
+                        &format!("\n{}{}", indent, member),
+                    ));
+                }
+                Some((last_key, last_value)) => {
+                    let indent = indentation_at(text, last_key.range.start.idx);
+                    let member = format!("{}: {}", quote_string(key), reindent(new_json, indent));
+                    let value_end = last_value.source_range().end;
+
+                    if value_end.line == object.range.end.line {
+                        edits.push(TextEdit::new(
+                            Range::new(value_end, value_end),
+                            &format!(", {}", member),
+                        ));
+                    } else {
+                        // Insert behind a trailing comment of the last member.
+                        let end = line_end(text, value_end.idx);
+                        edits.push(TextEdit::new(Range::new(value_end, value_end), ","));
+                        edits.push(TextEdit::new(
+                            range_at(text, end, end),
+                            &format!("\n{}{}", indent, member),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(edits)
+    }
+
+    /// Get the edits inserting `new_json` into the arrays at `path` before the
+    /// value at `idx`. An `idx` of the array length appends the value.
+    pub fn insert_array_value_edits(
+        &self,
+        text: &str,
+        path: &[PathType],
+        idx: usize,
+        new_json: &str,
+    ) -> ParseResult<Vec<TextEdit>> {
+        validate(new_json)?;
+        let arrays = self.value_at(path);
+        if arrays.is_empty() {
+            return Err(ParseError::PathNotFound);
+        }
+
+        let mut edits = vec![];
+        for array in arrays {
+            if !matches!(array.entry_type, EntryType::JSONArray(_)) {
+                return Err(ParseError::InvalidType);
+            }
+
+            let values = self.array_entries(array);
+            if idx > values.len() {
+                return Err(ParseError::PathNotFound);
+            }
+
+            let open_end = array.range.start.idx + 1;
+            let close_start = array.range.end.idx - 1;
+            match (values.get(idx), values.last()) {
+                (Some(next), _) => {
+                    let start = next.source_range().start;
+                    let indent = indentation_at(text, start.idx);
+                    let previous_line = match idx {
+                        0 => array.range.start.line,
+                        _ => values[idx - 1].source_range().end.line,
+                    };
+                    let separator = if previous_line == start.line {
+                        " ".to_owned()
+                    } else {
+                        format!("\n{}", indent)
+                    };
+                    edits.push(TextEdit::new(
+                        Range::new(start, start),
+                        &format!("{},{}", reindent(new_json, indent), separator),
+                    ));
+                }
+                (None, None) if array.range.start.line == array.range.end.line => {
+                    edits.push(TextEdit::new(
+                        range_at(text, open_end, close_start),
+                        new_json.trim(),
+                    ));
+                }
+                (None, None) => {
+                    let indent = format!(
+                        "{}{}",
+                        indentation_at(text, array.range.start.idx),
+                        detect_indent(text)
+                    );
+                    edits.push(TextEdit::new(
+                        range_at(text, open_end, open_end),
+                        &format!("\n{}{}", indent, reindent(new_json, &indent)),
+                    ));
+                }
+                (None, Some(last)) => {
+                    let range = last.source_range();
+                    let indent = indentation_at(text, range.start.idx);
+                    let value = reindent(new_json, indent);
+
+                    if range.end.line == array.range.end.line {
+                        edits.push(TextEdit::new(
+                            Range::new(range.end, range.end),
+                            &format!(", {}", value),
+                        ));
+                    } else {
+                        let end = line_end(text, range.end.idx);
+                        edits.push(TextEdit::new(Range::new(range.end, range.end), ","));
+                        edits.push(TextEdit::new(
+                            range_at(text, end, end),
+                            &format!("\n{}{}", indent, value),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(edits)
+    }
+
+    /// Get the edits renaming the key `old_name` to `new_name` in the objects
+    /// matching `query`, e.g. `$..http`. Duplicate keys are renamed as well.
+    /// Objects which already have a `new_name` key are left as they are and
+    /// reported as conflicts.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::{parse_json, tree::PathQuery, types::TextEdit};
+    /// let text = "{ \"http\": { \"timeoutMs\": 500 }, \"timeoutMs\": 10 }";
+    /// let tree = parse_json(text).unwrap();
+    /// let query = PathQuery::parse("$..http").unwrap();
+    /// let rename = tree.rename_key_edits(&query, "timeoutMs", "timeout_ms");
+    /// assert_eq!(
+    ///     "{ \"http\": { \"timeout_ms\": 500 }, \"timeoutMs\": 10 }",
+    ///     TextEdit::apply(text, &rename.edits)
+    /// );
+    /// assert!(rename.conflicts.is_empty());
+    /// ```
+    pub fn rename_key_edits(&self, query: &PathQuery, old_name: &str, new_name: &str) -> Rename {
+        let mut rename = Rename::default();
+        if old_name == new_name {
+            return rename;
+        }
+
+        // Keys are inside of the brackets of their object, so it is the
+        // innermost container around them, even for duplicate keys.
+        let object_of = |range: Range| {
+            self.container_at(range.start)
+                .filter(|entry| matches!(entry.entry_type, EntryType::JSONObject(_)))
+        };
+        let existing_in = |object: &Entry| {
+            self.keys
+                .iter()
+                .filter(|key| key.name == new_name)
+                .find(|key| object_of(key.range).is_some_and(|o| std::ptr::eq(o, object)))
+        };
+
+        for key in self.keys.iter().filter(|key| key.name == old_name) {
+            let object = match object_of(key.range) {
+                Some(object) => object,
+                None => continue,
+            };
+            let path = self.path_of(object);
+            if !query.matches(&path) {
+                continue;
+            }
+
+            match existing_in(object) {
+                Some(existing) => rename.conflicts.push(RenameConflict {
+                    path: path.with_key(old_name),
+                    range: key.source_range(),
+                    existing: existing.source_range(),
+                }),
+                None => rename
+                    .edits
+                    .push(TextEdit::new(key.source_range(), &quote_string(new_name))),
+            }
+        }
+
+        rename.edits.sort_by_key(|edit| edit.range.start.idx);
+        rename
+            .conflicts
+            .sort_by_key(|conflict| conflict.range.start.idx);
+        rename
+    }
+
+    /// Get the edits removing the object members or array values at `path`.
+    pub fn remove_edits(&self, text: &str, path: &[PathType]) -> ParseResult<Vec<TextEdit>> {
+        let entries = self.value_at(path);
+        if entries.is_empty() {
+            return Err(ParseError::PathNotFound);
+        }
+
+        let commas: Vec<Range> = tokenize::tokenize(text)?
+            .iter()
+            .filter_map(|token| match token {
+                TokenType::Comma(range) => Some(*range),
+                _ => None,
+            })
+            .collect();
+        let comma_between = |start: usize, end: usize| {
+            commas
+                .iter()
+                .find(|comma| start <= comma.start.idx && comma.end.idx <= end)
+                .cloned()
+        };
+
+        let mut edits = vec![];
+        for entry in entries {
+            let parent = self.parent(entry).ok_or(ParseError::InvalidType)?;
+            let siblings: Vec<&Entry> = match parent.entry_type {
+                EntryType::JSONObject(_) => self
+                    .object_entries(parent)
+                    .into_iter()
+                    .map(|(_, value)| value)
+                    .collect(),
+                _ => self.array_entries(parent),
+            };
+            let ranges: Vec<Range> = siblings.iter().map(|e| self.element_range(e)).collect();
+            let pos = siblings
+                .iter()
+                .position(|sibling| std::ptr::eq(*sibling, entry))
+                .ok_or(ParseError::Error)?;
+            let range = ranges[pos];
+
+            let before = &text[line_start(text, range.start.idx)..range.start.idx];
+            let after = text[range.end.idx..line_end(text, range.end.idx)].trim_start();
+            let after = after.strip_prefix(',').unwrap_or(after).trim_start();
+            let own_line =
+                before.trim().is_empty() && (after.is_empty() || after.starts_with("//"));
+
+            if own_line {
+                // Remove the whole lines including a trailing comment.
+                let start = line_start(text, range.start.idx);
+                let end = (line_end(text, range.end.idx) + 1).min(text.len());
+                edits.push(TextEdit::new(range_at(text, start, end), ""));
+
+                if pos + 1 == ranges.len() && pos > 0 {
+                    if let Some(comma) = comma_between(ranges[pos - 1].end.idx, range.start.idx) {
+                        edits.push(TextEdit::new(comma, ""));
+                    }
+                }
+            } else if let Some(next) = ranges.get(pos + 1) {
+                edits.push(TextEdit::new(Range::new(range.start, next.start), ""));
+            } else if pos > 0 {
+                edits.push(TextEdit::new(
+                    Range::new(ranges[pos - 1].end, range.end),
+                    "",
+                ));
+            } else {
+                edits.push(TextEdit::new(
+                    range_at(text, parent.range.start.idx + 1, parent.range.end.idx - 1),
+                    "",
+                ));
+            }
+        }
+
+        Ok(merge_removals(edits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_json;
+    use crate::tree::{PathQuery, PathType};
+    use crate::types::{ParseError, TextEdit};
+
+    const TEXT: &str = "{\n    // servers\n    \"servers\": [\n        {\n            \"host\": \"a\", // first\n            \"port\": 8080\n        },\n        { \"host\": \"b\", \"tags\": [1, 2, 3] }\n    ],\n    \"empty\": {}\n}\n";
+
+    #[test]
+    fn test_set_value_edits() {
+        let tree = parse_json(TEXT).unwrap();
+        let path = [
+            PathType::Object("servers"),
+            PathType::Wildcard,
+            PathType::Object("host"),
+        ];
+        let edits = tree
+            .set_value_edits(TEXT, &path, "{\n  \"name\": \"c\"\n}")
+            .unwrap();
+
+        assert_eq!(
+            "{\n    // servers\n    \"servers\": [\n        {\n            \"host\": {\n              \"name\": \"c\"\n            }, // first\n            \"port\": 8080\n        },\n        { \"host\": {\n          \"name\": \"c\"\n        }, \"tags\": [1, 2, 3] }\n    ],\n    \"empty\": {}\n}\n",
+            TextEdit::apply(TEXT, &edits)
+        );
+        assert!(tree.set_value_edits(TEXT, &path, "{ \"a\" ").is_err());
+        match tree.set_value_edits(TEXT, &path, "1, \"evil\": 2") {
+            Err(ParseError::TrailingContent(range)) => {
+                assert_eq!((1, 12), (range.start.char, range.end.char));
+            }
+            res => panic!("Should be trailing content, got '{:?}'", res),
+        }
+        match tree.set_value_edits(TEXT, &path, "1 // c") {
+            Err(ParseError::UnexpectedCharacter(range)) => {
+                assert_eq!((2, 6), (range.start.char, range.end.char));
+            }
+            res => panic!("Should be a comment, got '{:?}'", res),
+        }
+        assert!(tree.set_value_edits(TEXT, &path, "[1], [2]").is_err());
+        assert!(tree.set_value_edits(TEXT, &path, "").is_err());
+    }
+
+    #[test]
+    fn test_insert_key_edits() {
+        let tree = parse_json(TEXT).unwrap();
+        let first = [PathType::Object("servers"), PathType::Array(0)];
+        let second = [PathType::Object("servers"), PathType::Array(1)];
+
+        let mut edits = tree.insert_key_edits(TEXT, &first, "tls", "true").unwrap();
+        edits.extend(tree.insert_key_edits(TEXT, &second, "port", "80").unwrap());
+        edits.extend(
+            tree.insert_key_edits(TEXT, &[PathType::Object("empty")], "a\"b", "[]")
+                .unwrap(),
+        );
+
+        assert_eq!(
+            "{\n    // servers\n    \"servers\": [\n        {\n            \"host\": \"a\", // first\n            \"port\": 8080,\n            \"tls\": true\n        },\n        { \"host\": \"b\", \"tags\": [1, 2, 3], \"port\": 80 }\n    ],\n    \"empty\": { \"a\\\"b\": [] }\n}\n",
+            TextEdit::apply(TEXT, &edits)
+        );
+        assert!(tree
+            .insert_key_edits(TEXT, &first, "tls", "true, \"evil\": 2")
+            .is_err());
+        assert!(tree
+            .insert_key_edits(TEXT, &first, "tls", "true /* c */")
+            .is_err());
+    }
+
+    #[test]
+    fn test_insert_array_value_edits() {
+        let tree = parse_json(TEXT).unwrap();
+        let servers = [PathType::Object("servers")];
+        let tags = [
+            PathType::Object("servers"),
+            PathType::Array(1),
+            PathType::Object("tags"),
+        ];
+
+        let mut edits = tree
+            .insert_array_value_edits(TEXT, &servers, 2, "{ \"host\": \"c\" }")
+            .unwrap();
+        edits.extend(tree.insert_array_value_edits(TEXT, &tags, 0, "0").unwrap());
+        edits.extend(tree.insert_array_value_edits(TEXT, &tags, 3, "4").unwrap());
+
+        assert_eq!(
+            "{\n    // servers\n    \"servers\": [\n        {\n            \"host\": \"a\", // first\n            \"port\": 8080\n        },\n        { \"host\": \"b\", \"tags\": [0, 1, 2, 3, 4] },\n        { \"host\": \"c\" }\n    ],\n    \"empty\": {}\n}\n",
+            TextEdit::apply(TEXT, &edits)
+        );
+        assert!(tree.insert_array_value_edits(TEXT, &tags, 5, "5").is_err());
+        assert!(tree
+            .insert_array_value_edits(TEXT, &tags, 0, "0, 9")
+            .is_err());
+        assert!(tree
+            .insert_array_value_edits(TEXT, &tags, 0, "{}]")
+            .is_err());
+    }
+
+    #[test]
+    fn test_remove_edits() {
+        let tree = parse_json(TEXT).unwrap();
+        let path = [
+            PathType::Object("servers"),
+            PathType::Array(0),
+            PathType::Object("port"),
+        ];
+        let mut edits = tree.remove_edits(TEXT, &path).unwrap();
+        let path = [
+            PathType::Object("servers"),
+            PathType::Array(1),
+            PathType::Object("tags"),
+            PathType::Array(1),
+        ];
+        edits.extend(tree.remove_edits(TEXT, &path).unwrap());
+        edits.extend(
+            tree.remove_edits(TEXT, &[PathType::Object("empty")])
+                .unwrap(),
+        );
+
+        assert_eq!(
+            "{\n    // servers\n    \"servers\": [\n        {\n            \"host\": \"a\" // first\n        },\n        { \"host\": \"b\", \"tags\": [1, 3] }\n    ]\n}\n",
+            TextEdit::apply(TEXT, &edits)
+        );
+    }
+
+    #[test]
+    fn test_rename_key_edits() {
+        let text = "{\n  \"http\": { \"timeoutMs\": 1, \"timeoutMs\": 2 },\n  \"api\": { \"http\": { \"timeoutMs\": 3, \"a\\\"b\": 4 } },\n  \"db\": { \"timeoutMs\": 5 }\n}";
+        let tree = parse_json(text).unwrap();
+        let query = PathQuery::parse("$..http").unwrap();
+
+        let rename = tree.rename_key_edits(&query, "timeoutMs", "a\"b");
+        assert_eq!(
+            "{\n  \"http\": { \"a\\\"b\": 1, \"a\\\"b\": 2 },\n  \"api\": { \"http\": { \"timeoutMs\": 3, \"a\\\"b\": 4 } },\n  \"db\": { \"timeoutMs\": 5 }\n}",
+            TextEdit::apply(text, &rename.edits)
+        );
+
+        assert_eq!(1, rename.conflicts.len());
+        let conflict = &rename.conflicts[0];
+        assert_eq!("$.api.http.timeoutMs", conflict.path.to_string());
+        assert_eq!(
+            ("\"timeoutMs\"", "\"a\\\"b\""),
+            (
+                &text[conflict.range.start.idx..conflict.range.end.idx],
+                &text[conflict.existing.start.idx..conflict.existing.end.idx]
+            )
+        );
+        assert!(tree
+            .rename_key_edits(&query, "timeoutMs", "timeoutMs")
+            .edits
+            .is_empty());
+    }
+}
@@ -0,0 +1,566 @@
+use super::lex::{lex, Token, TokenKind};
+use super::tree::{Entry, EntryType, JsonPath, Key, Tree};
+use super::types::{Position, Range};
+use super::writer::{write_entry, WriteOptions};
+use std::collections::HashSet;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FoldingKind {
+    /// An object or array.
+    Region,
+    /// Consecutive lines with only a comment.
+    Comment,
+}
+
+/// Lines which can be folded, from `start_line` to `end_line` including both.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FoldingRange {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub kind: FoldingKind,
+}
+
+/// The kind of a value, with integers told apart from other numbers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValueKind {
+    Object,
+    Array,
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Null,
+}
+
+impl ValueKind {
+    pub fn of(entry: &Entry) -> ValueKind {
+        match entry.entry_type {
+            EntryType::JSONObject(_) => ValueKind::Object,
+            EntryType::JSONArray(_) => ValueKind::Array,
+            EntryType::String(_) => ValueKind::String,
+            EntryType::Int(_) => ValueKind::Integer,
+            EntryType::Float(_) => ValueKind::Number,
+            EntryType::Bool(_) => ValueKind::Boolean,
+            EntryType::Null => ValueKind::Null,
+        }
+    }
+
+    /// The name of the kind as in json schema, e.g. `integer`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueKind::Object => "object",
+            ValueKind::Array => "array",
+            ValueKind::String => "string",
+            ValueKind::Integer => "integer",
+            ValueKind::Number => "number",
+            ValueKind::Boolean => "boolean",
+            ValueKind::Null => "null",
+        }
+    }
+}
+
+/// An entry of the outline of a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentSymbol {
+    /// The key of an object member, or the index of an array element.
+    pub name: String,
+    pub kind: ValueKind,
+    /// The value, or `{…}` and `[…]` for objects and arrays.
+    pub detail: String,
+    /// The range of the key and the value.
+    pub range: Range,
+    /// The range of the key, or of the value for array elements.
+    pub selection_range: Range,
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// The longest preview of a value in `Hover`, in characters.
+const HOVER_PREVIEW_LEN: usize = 40;
+
+/// What to show when hovering over a key or a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hover {
+    pub path: JsonPath,
+    pub kind: ValueKind,
+    /// The compact value, cut after 40 characters. Cut objects and arrays end
+    /// with the number of their children, e.g. `[1,2,3,…] (250 items)`.
+    pub preview: String,
+    /// The range of the key or the value hovered, including quotes.
+    pub range: Range,
+    /// The comment on the lines directly above the key, without `//`.
+    pub comment: Option<String>,
+}
+
+fn hover_preview(tree: &Tree, entry: &Entry) -> String {
+    let text = write_entry(tree, entry, &WriteOptions::compact());
+    if text.chars().count() <= HOVER_PREVIEW_LEN {
+        return text;
+    }
+
+    let cut: String = text.chars().take(HOVER_PREVIEW_LEN).collect();
+    match &entry.entry_type {
+        EntryType::JSONObject(members) => format!("{}…}} ({} keys)", cut, members.len()),
+        EntryType::JSONArray(items) => format!("{}…] ({} items)", cut, items.len()),
+        _ => format!("{}…", cut),
+    }
+}
+
+fn preview(tree: &Tree, entry: &Entry) -> String {
+    match entry.entry_type {
+        EntryType::JSONObject(_) => "{…}".to_owned(),
+        EntryType::JSONArray(_) => "[…]".to_owned(),
+        _ => write_entry(tree, entry, &WriteOptions::compact()),
+    }
+}
+
+fn contains(range: Range, idx: usize) -> bool {
+    range.start.idx <= idx && idx <= range.end.idx
+}
+
+fn span(start: Range, end: Range) -> Range {
+    Range::new(start.start, end.end)
+}
+
+/// Get the comments which are alone on their lines.
+fn line_comments(text: &str) -> Vec<Token<'_>> {
+    let mut comments = vec![];
+    let mut line_has_content = false;
+    let mut current_line = 0;
+
+    lex(text).for_each(|token| {
+        if token.range.start.line != current_line {
+            current_line = token.range.start.line;
+            line_has_content = false;
+        }
+        match token.kind {
+            TokenKind::Comment if !line_has_content => {
+                comments.push(token);
+                line_has_content = true;
+            }
+            TokenKind::Whitespace => {
+                if token.range.end.line != current_line {
+                    current_line = token.range.end.line;
+                    line_has_content = false;
+                }
+            }
+            _ => line_has_content = true,
+        }
+    });
+    comments
+}
+
+/// Get the lines of runs of comments which are alone on their lines.
+fn comment_blocks(text: &str) -> Vec<(usize, usize)> {
+    let mut blocks: Vec<(usize, usize)> = vec![];
+    line_comments(text).iter().for_each(|comment| {
+        let line = comment.range.start.line;
+        match blocks.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => blocks.push((line, line)),
+        }
+    });
+
+    blocks.retain(|(start, end)| start < end);
+    blocks
+}
+
+/// Get the comment on the lines directly above a key which starts its line.
+fn comment_above(text: &str, key: &Key) -> Option<String> {
+    let start = key.source_range().start;
+    let line_start = text[..start.idx].rfind('\n').map_or(0, |pos| pos + 1);
+    if !text[line_start..start.idx].trim().is_empty() {
+        return None;
+    }
+
+    let comments = line_comments(&text[..line_start]);
+    let mut line = start.line;
+    let mut lines: Vec<&str> = comments
+        .iter()
+        .rev()
+        .take_while(|comment| {
+            let above = comment.range.start.line + 1 == line;
+            line = comment.range.start.line;
+            above
+        })
+        .map(|comment| comment.text.trim_start_matches('/').trim())
+        .collect();
+    lines.reverse();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+impl Tree {
+    /// Get the folding ranges of multi-line objects and arrays and of blocks
+    /// of comments in `text`, the text of the tree. The ranges are sorted by
+    /// start line. Of containers starting or ending on the same line, only the
+    /// outermost is kept, as editors fold one range per line.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::{editor::{FoldingKind, FoldingRange}, parse_json};
+    /// let text = "{\n  \"a\": [\n    1\n  ]\n}";
+    /// let tree = parse_json(text).unwrap();
+    /// assert_eq!(
+    ///     FoldingRange { start_line: 1, end_line: 3, kind: FoldingKind::Region },
+    ///     tree.folding_ranges(text)[1]
+    /// );
+    /// ```
+    pub fn folding_ranges(&self, text: &str) -> Vec<FoldingRange> {
+        let mut ranges: Vec<FoldingRange> = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry.entry_type,
+                    EntryType::JSONObject(_) | EntryType::JSONArray(_)
+                )
+            })
+            .filter(|entry| entry.range.start.line < entry.range.end.line)
+            .map(|entry| FoldingRange {
+                start_line: entry.range.start.line,
+                end_line: entry.range.end.line,
+                kind: FoldingKind::Region,
+            })
+            .chain(
+                comment_blocks(text)
+                    .into_iter()
+                    .map(|(start_line, end_line)| FoldingRange {
+                        start_line,
+                        end_line,
+                        kind: FoldingKind::Comment,
+                    }),
+            )
+            .collect();
+
+        ranges.sort_by_key(|range| (range.start_line, std::cmp::Reverse(range.end_line)));
+        ranges.dedup_by_key(|range| range.start_line);
+        let mut end_lines = HashSet::new();
+        ranges.retain(|range| end_lines.insert(range.end_line));
+        ranges
+    }
+
+    /// Get the outline of the document: a symbol for every member of the root
+    /// object, with the members of objects and the elements of arrays as
+    /// children, in source order.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::{editor::ValueKind, parse_json};
+    /// let tree = parse_json("{ \"a\": [true] }").unwrap();
+    /// let symbols = tree.document_symbols();
+    /// assert_eq!("a", symbols[0].name);
+    /// assert_eq!(ValueKind::Array, symbols[0].kind);
+    /// assert_eq!("0", symbols[0].children[0].name);
+    /// assert_eq!("true", symbols[0].children[0].detail);
+    /// ```
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        self.root()
+            .map(|root| self.symbols(root))
+            .unwrap_or_default()
+    }
+
+    fn symbols(&self, entry: &Entry) -> Vec<DocumentSymbol> {
+        match entry.entry_type {
+            EntryType::JSONObject(_) => self
+                .object_entries(entry)
+                .into_iter()
+                .map(|(key, value)| {
+                    let range = span(self.key_source_range(key), self.entry_source_range(value));
+                    self.symbol(key.name.clone(), range, self.key_source_range(key), value)
+                })
+                .collect(),
+            EntryType::JSONArray(_) => self
+                .array_entries(entry)
+                .into_iter()
+                .enumerate()
+                .map(|(idx, value)| {
+                    let range = self.entry_source_range(value);
+                    self.symbol(idx.to_string(), range, range, value)
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn symbol(
+        &self,
+        name: String,
+        range: Range,
+        selection_range: Range,
+        entry: &Entry,
+    ) -> DocumentSymbol {
+        DocumentSymbol {
+            name,
+            kind: ValueKind::of(entry),
+            detail: preview(self, entry),
+            range,
+            selection_range,
+            children: self.symbols(entry),
+        }
+    }
+
+    /// Get the index in `entries` of the innermost entry at a position, with
+    /// its key when the position is on the key of the entry. Positions at the
+    /// start or the end of a range are inside of it.
+    pub fn entry_at(&self, position: Position) -> Option<(usize, Option<&Key>)> {
+        let on_key = self.keys.iter().enumerate().find_map(|(key_idx, key)| {
+            if !contains(self.key_source_range(key), position.idx) {
+                return None;
+            }
+            self.entries
+                .iter()
+                .position(|entry| entry.key == Some(key_idx))
+                .map(|idx| (idx, Some(key)))
+        });
+        on_key.or_else(|| {
+            self.entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| contains(self.entry_source_range(entry), position.idx))
+                .min_by_key(|(_, entry)| entry.range.end.idx - entry.range.start.idx)
+                .map(|(idx, _)| (idx, None))
+        })
+    }
+
+    /// Get the ranges for expanding a selection from a position, from the
+    /// innermost to the root: the key or value at the position, the key with
+    /// its value, the containing object or array, and so on. From a key, the
+    /// value alone is skipped as it does not contain the key. Empty if the
+    /// position is outside of the root.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::{edit::position_at, parse_json};
+    /// let text = "{ \"a\": [1, 2] }";
+    /// let tree = parse_json(text).unwrap();
+    /// let ranges: Vec<&str> = tree
+    ///     .selection_ranges(position_at(text, 12))
+    ///     .iter()
+    ///     .map(|range| &text[range.start.idx..range.end.idx])
+    ///     .collect();
+    /// assert_eq!(vec!["2", "[1, 2]", "\"a\": [1, 2]", text], ranges);
+    /// ```
+    pub fn selection_ranges(&self, position: Position) -> Vec<Range> {
+        let (entry, key) = match self.entry_at(position) {
+            Some((idx, key)) => (&self.entries[idx], key),
+            None => return vec![],
+        };
+        let mut ranges = vec![];
+        let mut current = Some(entry);
+        if let Some(key) = key {
+            ranges.push(self.key_source_range(key));
+            ranges.push(span(
+                self.key_source_range(key),
+                self.entry_source_range(entry),
+            ));
+            current = self.parent(entry);
+        }
+        while let Some(entry) = current {
+            ranges.push(self.entry_source_range(entry));
+            if let Some(key) = entry.key.and_then(|key| self.keys.get(key)) {
+                ranges.push(span(
+                    self.key_source_range(key),
+                    self.entry_source_range(entry),
+                ));
+            }
+            current = self.parent(entry);
+        }
+        ranges
+    }
+
+    /// Get what to show when hovering over a position of `text`, the text of
+    /// the tree: the path, kind and preview of the value at the position and
+    /// the comment above its key.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::{edit::position_at, parse_json_tolerant};
+    /// let text = "{\n  // in seconds\n  \"timeout\": 30\n}";
+    /// let tree = parse_json_tolerant(text);
+    /// let hover = tree.hover(text, position_at(text, 32)).unwrap();
+    /// assert_eq!("$.timeout", hover.path.to_string());
+    /// assert_eq!("integer", hover.kind.name());
+    /// assert_eq!("30", hover.preview);
+    /// assert_eq!(Some("in seconds".to_owned()), hover.comment);
+    /// ```
+    pub fn hover(&self, text: &str, position: Position) -> Option<Hover> {
+        let (idx, key) = self.entry_at(position)?;
+        let entry = &self.entries[idx];
+        let member_key = entry.key.and_then(|key| self.keys.get(key));
+
+        Some(Hover {
+            path: self.path_of(entry),
+            kind: ValueKind::of(entry),
+            preview: hover_preview(self, entry),
+            range: key.map_or_else(
+                || self.entry_source_range(entry),
+                |key| self.key_source_range(key),
+            ),
+            comment: member_key.and_then(|key| comment_above(text, key)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FoldingKind, FoldingRange, ValueKind};
+    use crate::edit::position_at;
+    use crate::types::Position;
+    use crate::{parse_json, parse_json_tolerant};
+
+    fn range(start_line: usize, end_line: usize, kind: FoldingKind) -> FoldingRange {
+        FoldingRange {
+            start_line,
+            end_line,
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let text = "// config\n// of the app\n{\n  \"a\": [{\n    \"b\": 1 // one\n  }],\n  // c\n  \"c\": { \"d\": [] },\n  \"e\": { \"f\": [\n    1\n  ]}\n}";
+        let tree = parse_json_tolerant(text);
+        assert_eq!(
+            vec![
+                range(0, 1, FoldingKind::Comment),
+                range(2, 11, FoldingKind::Region),
+                range(3, 5, FoldingKind::Region),
+                range(8, 10, FoldingKind::Region),
+            ],
+            tree.folding_ranges(text)
+        );
+
+        // Nested containers closing on the same line fold as the outermost.
+        let text = "{\n  \"a\": {\n    \"b\": [\n      1\n    ]},\n  \"c\": {\n    \"d\": [\n      1\n    ]}\n}";
+        let tree = parse_json_tolerant(text);
+        assert_eq!(
+            vec![
+                range(0, 9, FoldingKind::Region),
+                range(1, 4, FoldingKind::Region),
+                range(5, 8, FoldingKind::Region),
+            ],
+            tree.folding_ranges(text)
+        );
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let text = "{\n  \"a\": { \"b\": 1.5 },\n  \"c\": [\"x\", null],\n  \"d\": {}\n}";
+        let tree = parse_json(text).unwrap();
+        let symbols = tree.document_symbols();
+
+        let outline: Vec<(&str, ValueKind, &str, usize)> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.detail.as_str(), s.children.len()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("a", ValueKind::Object, "{…}", 1),
+                ("c", ValueKind::Array, "[…]", 2),
+                ("d", ValueKind::Object, "{…}", 0),
+            ],
+            outline
+        );
+
+        let a = &symbols[0];
+        assert_eq!((4, 21), (a.range.start.idx, a.range.end.idx));
+        assert_eq!(
+            (4, 7),
+            (a.selection_range.start.idx, a.selection_range.end.idx)
+        );
+        assert_eq!("1.5", a.children[0].detail);
+
+        let x = &symbols[1].children[0];
+        assert_eq!(
+            ("0", ValueKind::String, "\"x\""),
+            (x.name.as_str(), x.kind, x.detail.as_str())
+        );
+        assert_eq!(x.range, x.selection_range);
+        let null = &symbols[1].children[1];
+        assert_eq!(
+            ("1", ValueKind::Null, 2),
+            (null.name.as_str(), null.kind, null.range.start.line)
+        );
+    }
+
+    #[test]
+    fn test_selection_ranges() {
+        let text = "{\n  \"a\": [{ \"bé\": true }],\n  \"c\": 1\n}";
+        let tree = parse_json(text).unwrap();
+        let selections = |idx: usize| -> Vec<&str> {
+            tree.selection_ranges(position_at(text, idx))
+                .iter()
+                .map(|range| &text[range.start.idx..range.end.idx])
+                .collect()
+        };
+
+        let value = text.find("true").unwrap();
+        assert_eq!(
+            vec![
+                "true",
+                "\"bé\": true",
+                "{ \"bé\": true }",
+                "[{ \"bé\": true }]",
+                "\"a\": [{ \"bé\": true }]",
+                text,
+            ],
+            selections(value + 2)
+        );
+        assert_eq!(
+            vec!["\"bé\"", "\"bé\": true", "{ \"bé\": true }"],
+            selections(value - 5)[..3].to_vec()
+        );
+        assert_eq!(
+            vec!["\"c\"", "\"c\": 1", text],
+            selections(text.find("\"c").unwrap())
+        );
+        assert!(tree
+            .selection_ranges(Position::new(4, 0, text.len() + 1))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_hover() {
+        let text = "{\n  // The servers,\n  // in order\n  \"servers\": [\n    { \"port\": 8080 },\n    \"abcdefghijklmnopqrstuvwxyz abcdefghijklmnopqrstuvwxyz\"\n  ],\n  \"ids\": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18]\n}";
+        let tree = parse_json_tolerant(text);
+        let hover = |pattern: &str, offset: usize| {
+            let idx = text.find(pattern).unwrap() + offset;
+            tree.hover(text, position_at(text, idx)).unwrap()
+        };
+
+        let port = hover("8080", 1);
+        assert_eq!("$.servers[0].port", port.path.to_string());
+        assert_eq!(
+            (ValueKind::Integer, "8080"),
+            (port.kind, port.preview.as_str())
+        );
+        assert_eq!("8080", &text[port.range.start.idx..port.range.end.idx]);
+        assert_eq!(None, port.comment);
+
+        let servers = hover("\"servers", 3);
+        assert_eq!("$.servers", servers.path.to_string());
+        assert_eq!(
+            "\"servers\"",
+            &text[servers.range.start.idx..servers.range.end.idx]
+        );
+        assert_eq!(Some("The servers,\nin order"), servers.comment.as_deref());
+
+        let long = hover("\"abc", 5);
+        assert_eq!(ValueKind::String, long.kind);
+        assert_eq!("\"abcdefghijklmnopqrstuvwxyz abcdefghijkl…", long.preview);
+
+        let ids = hover("[1,", 0);
+        assert_eq!(
+            "[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,…] (18 items)",
+            ids.preview
+        );
+        assert_eq!(None, ids.comment);
+        assert!(tree
+            .hover(text, Position::new(9, 0, text.len() + 1))
+            .is_none());
+    }
+}
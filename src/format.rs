@@ -0,0 +1,414 @@
+use super::cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+use super::parse_cst;
+use super::types::{ParseResult, Range, TextEdit};
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Number of spaces per indentation level, ignored when `use_tabs` is set.
+    pub indent_width: usize,
+    pub use_tabs: bool,
+    /// Objects and arrays without comments are kept on one line if they fit
+    /// into this width. `0` always breaks non empty objects and arrays.
+    pub max_line_width: usize,
+    pub trailing_newline: bool,
+    pub space_after_colon: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            indent_width: 2,
+            use_tabs: false,
+            max_line_width: 80,
+            trailing_newline: true,
+            space_after_colon: true,
+        }
+    }
+}
+
+/// A value or member of a container with the comments around it.
+struct Item<'a> {
+    /// Comments on the lines above, with a flag for a blank line before them.
+    leading: Vec<(&'a SyntaxToken, bool)>,
+    element: &'a SyntaxElement,
+    /// Comment on the same line after the item.
+    trailing: Option<&'a SyntaxToken>,
+    blank_before: bool,
+}
+
+/// Group the children of a container or document into items. Returns the
+/// items and the comments after the last item.
+fn collect_items<'a>(
+    children: &'a [SyntaxElement],
+) -> (Vec<Item<'a>>, Vec<(&'a SyntaxToken, bool)>) {
+    let mut items: Vec<Item> = vec![];
+    let mut pending = vec![];
+    let mut newlines = 0;
+
+    children.iter().for_each(|child| match child {
+        SyntaxElement::Token(token) => match token.kind {
+            SyntaxKind::Whitespace => newlines += token.text.matches('\n').count(),
+            SyntaxKind::Comment => {
+                let blank = newlines > 1 && !(items.is_empty() && pending.is_empty());
+                match items.last_mut() {
+                    Some(last)
+                        if newlines == 0 && pending.is_empty() && last.trailing.is_none() =>
+                    {
+                        last.trailing = Some(token)
+                    }
+                    _ => pending.push((token, blank)),
+                }
+                newlines = 0;
+            }
+            SyntaxKind::Comma => {}
+            _ => {
+                items.push(Item {
+                    blank_before: newlines > 1 && !(items.is_empty() && pending.is_empty()),
+                    leading: std::mem::take(&mut pending),
+                    element: child,
+                    trailing: None,
+                });
+                newlines = 0;
+            }
+        },
+        SyntaxElement::Node(_) => {
+            items.push(Item {
+                blank_before: newlines > 1 && !(items.is_empty() && pending.is_empty()),
+                leading: std::mem::take(&mut pending),
+                element: child,
+                trailing: None,
+            });
+            newlines = 0;
+        }
+    });
+
+    (items, pending)
+}
+
+fn has_comments(node: &SyntaxNode) -> bool {
+    node.tokens()
+        .iter()
+        .any(|token| token.kind == SyntaxKind::Comment)
+}
+
+/// Original range and output position of a written token.
+struct Anchor {
+    range: Range,
+    start: usize,
+    end: usize,
+}
+
+struct Formatter<'a> {
+    options: &'a FormatOptions,
+    out: String,
+    anchors: Vec<Anchor>,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(options: &'a FormatOptions) -> Formatter<'a> {
+        Formatter {
+            options,
+            out: String::new(),
+            anchors: vec![],
+        }
+    }
+
+    fn token(&mut self, token: &SyntaxToken) {
+        let start = self.out.len();
+        self.out.push_str(&token.text);
+        self.anchors.push(Anchor {
+            range: token.range,
+            start,
+            end: self.out.len(),
+        });
+    }
+
+    fn column(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |idx| idx + 1);
+        self.out[line_start..].chars().count()
+    }
+
+    /// Start a new line at `depth`, unless the current line is still empty.
+    fn line_break(&mut self, depth: usize, blank: bool) {
+        let token_end = self.anchors.last().map_or(0, |anchor| anchor.end);
+        let trimmed = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed.max(token_end));
+
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        if blank && !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+
+        (0..depth).for_each(|_| {
+            if self.options.use_tabs {
+                self.out.push('\t');
+            } else {
+                (0..self.options.indent_width).for_each(|_| self.out.push(' '));
+            }
+        });
+    }
+
+    fn element(&mut self, element: &SyntaxElement, depth: usize) {
+        match element {
+            SyntaxElement::Token(token) => self.token(token),
+            SyntaxElement::Node(node) => match node.kind {
+                SyntaxKind::Member => self.member(node, depth),
+                _ => self.container(node, depth),
+            },
+        }
+    }
+
+    fn member(&mut self, node: &SyntaxNode, depth: usize) {
+        node.children.iter().for_each(|child| match child {
+            SyntaxElement::Token(token) if token.kind == SyntaxKind::Whitespace => {}
+            SyntaxElement::Token(token) if token.kind == SyntaxKind::Comment => {
+                self.out.push(' ');
+                self.token(token);
+                self.line_break(depth + 1, false);
+            }
+            SyntaxElement::Token(token) if token.kind == SyntaxKind::Colon => {
+                self.token(token);
+                if self.options.space_after_colon {
+                    self.out.push(' ');
+                }
+            }
+            _ => self.element(child, depth),
+        });
+    }
+
+    fn inline(&mut self, element: &SyntaxElement) {
+        match element {
+            SyntaxElement::Token(token) => self.token(token),
+            SyntaxElement::Node(node) if node.kind == SyntaxKind::Member => self.member(node, 0),
+            SyntaxElement::Node(node) => self.inline_container(node),
+        }
+    }
+
+    fn inline_container(&mut self, node: &SyntaxNode) {
+        let children = &node.children;
+        let (items, _) = collect_items(&children[1..children.len() - 1]);
+        let padding = if node.kind == SyntaxKind::Object && !items.is_empty() {
+            " "
+        } else {
+            ""
+        };
+
+        self.element(&children[0], 0);
+        self.out.push_str(padding);
+        items.iter().enumerate().for_each(|(idx, item)| {
+            if idx > 0 {
+                self.out.push_str(", ");
+            }
+            self.inline(item.element);
+        });
+        self.out.push_str(padding);
+        self.element(&children[children.len() - 1], 0);
+    }
+
+    fn fits_inline(&self, node: &SyntaxNode) -> bool {
+        if has_comments(node) {
+            return false;
+        }
+
+        let mut scratch = Formatter::new(self.options);
+        scratch.inline_container(node);
+        let width = scratch.out.chars().count();
+
+        width <= 2 || self.column() + width <= self.options.max_line_width
+    }
+
+    fn container(&mut self, node: &SyntaxNode, depth: usize) {
+        let children = &node.children;
+        let closed = matches!(
+            children.last(),
+            Some(SyntaxElement::Token(token))
+                if token.kind == SyntaxKind::ObjectClose || token.kind == SyntaxKind::ArrayClose
+        ) && children.len() > 1;
+
+        if closed && self.fits_inline(node) {
+            return self.inline_container(node);
+        }
+
+        let inner_end = if closed {
+            children.len() - 1
+        } else {
+            children.len()
+        };
+        let (items, dangling) = collect_items(&children[1..inner_end]);
+
+        self.element(&children[0], depth);
+        let count = items.len();
+        items.into_iter().enumerate().for_each(|(idx, item)| {
+            self.item(item, depth + 1, idx + 1 < count);
+        });
+        self.comments(&dangling, depth + 1);
+        if closed {
+            self.line_break(depth, false);
+            self.element(&children[inner_end], depth);
+        }
+    }
+
+    fn comments(&mut self, comments: &[(&SyntaxToken, bool)], depth: usize) {
+        comments.iter().for_each(|(comment, blank)| {
+            self.line_break(depth, *blank);
+            self.token(comment);
+        });
+    }
+
+    fn item(&mut self, item: Item, depth: usize, comma: bool) {
+        self.comments(&item.leading, depth);
+        self.line_break(depth, item.blank_before);
+        self.element(item.element, depth);
+        if comma {
+            self.out.push(',');
+        }
+        if let Some(trailing) = item.trailing {
+            self.out.push(' ');
+            self.token(trailing);
+        }
+    }
+
+    fn document(&mut self, node: &SyntaxNode) {
+        let (items, dangling) = collect_items(&node.children);
+        items.into_iter().for_each(|item| self.item(item, 0, false));
+        self.comments(&dangling, 0);
+
+        self.line_break(0, false);
+        if !self.options.trailing_newline {
+            self.out.truncate(self.out.trim_end_matches('\n').len());
+        }
+    }
+}
+
+fn format_cst(cst: &SyntaxNode, options: &FormatOptions) -> (String, Vec<TextEdit>) {
+    let mut formatter = Formatter::new(options);
+    formatter.document(cst);
+
+    let text = cst.to_string();
+
+    // Everything between two written tokens is whitespace or a comma, an edit
+    // is created for every gap that changed.
+    let start = Anchor {
+        range: Range::new(cst.range.start, cst.range.start),
+        start: 0,
+        end: 0,
+    };
+    let end = Anchor {
+        range: Range::new(cst.range.end, cst.range.end),
+        start: formatter.out.len(),
+        end: formatter.out.len(),
+    };
+    let mut anchors = vec![start];
+    anchors.append(&mut formatter.anchors);
+    anchors.push(end);
+
+    let edits = anchors
+        .windows(2)
+        .filter_map(|pair| {
+            let (prev, next) = (&pair[0], &pair[1]);
+            let old = &text[prev.range.end.idx..next.range.start.idx];
+            let new = &formatter.out[prev.end..next.start];
+            if old == new {
+                return None;
+            }
+            Some(TextEdit::new(
+                Range::new(prev.range.end, next.range.start),
+                new,
+            ))
+        })
+        .collect();
+
+    (formatter.out, edits)
+}
+
+/// Format a json text. Comments are kept.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::format::{format, FormatOptions};
+/// let text = "{\"a\":[1,2],\n// comment\n\"b\":{}}";
+/// assert_eq!(
+///     "{\n  \"a\": [1, 2],\n  // comment\n  \"b\": {}\n}\n",
+///     format(text, &FormatOptions::default()).unwrap()
+/// );
+/// ```
+pub fn format(text: &str, options: &FormatOptions) -> ParseResult<String> {
+    parse_cst(text).map(|cst| format_cst(&cst, options).0)
+}
+
+/// Get the edits which turn a json text into its formatted form.
+pub fn format_edits(text: &str, options: &FormatOptions) -> ParseResult<Vec<TextEdit>> {
+    parse_cst(text).map(|cst| format_cst(&cst, options).1)
+}
+
+/// Get the edits which format the part of a json text inside `range`.
+pub fn format_range(
+    text: &str,
+    range: Range,
+    options: &FormatOptions,
+) -> ParseResult<Vec<TextEdit>> {
+    format_edits(text, options).map(|edits| {
+        edits
+            .into_iter()
+            .filter(|edit| {
+                edit.range.start.idx <= range.end.idx && range.start.idx <= edit.range.end.idx
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format, format_edits, format_range, FormatOptions};
+    use crate::types::{Position, Range, TextEdit};
+
+    #[test]
+    fn test_format() {
+        let text = "// head\n\n{\"a\":[1,2],   // trailing a\n// lead b\n\n\n\"b\":{\"c\":[true,false,null,\"a much longer string value\",\"another long string value\"]},\"e\":[] , \"f\": { } \n // dangling\n}  // end";
+        let formatted = format(text, &FormatOptions::default()).unwrap();
+        assert_eq!(
+            "// head\n\n{\n  \"a\": [1, 2], // trailing a\n  // lead b\n\n  \"b\": {\n    \"c\": [\n      true,\n      false,\n      null,\n      \"a much longer string value\",\n      \"another long string value\"\n    ]\n  },\n  \"e\": [],\n  \"f\": {}\n  // dangling\n} // end\n",
+            formatted
+        );
+
+        let edits = format_edits(text, &FormatOptions::default()).unwrap();
+        assert_eq!(formatted, TextEdit::apply(text, &edits));
+        assert_eq!(
+            formatted,
+            format(&formatted, &FormatOptions::default()).unwrap()
+        );
+        assert!(format_edits(&formatted, &FormatOptions::default())
+            .unwrap()
+            .is_empty());
+
+        let options = FormatOptions {
+            use_tabs: true,
+            max_line_width: 0,
+            trailing_newline: false,
+            space_after_colon: false,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            "{\n\t\"a\":[\n\t\t1\n\t],\n\t\"b\":{}\n}",
+            format("{ \"a\": [1], \"b\": {} }\n", &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_range() {
+        let text = "{\n\"a\":   1,\n    \"b\":{ \"c\" :2 }\n}";
+        let range = Range::new(Position::new(2, 0, 12), Position::new(2, 18, 30));
+        let options = FormatOptions {
+            max_line_width: 20,
+            ..FormatOptions::default()
+        };
+        let edits = format_range(text, range, &options).unwrap();
+
+        assert_eq!(
+            "{\n\"a\":   1,\n  \"b\": { \"c\": 2 }\n}",
+            TextEdit::apply(text, &edits)
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use super::parser::tokenize::{self, TokenType};
+use super::types::{Position, Range};
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    /// A `//` comment up to the end of its line.
+    Comment,
+    ObjectOpen,
+    ObjectClose,
+    ArrayOpen,
+    ArrayClose,
+    Comma,
+    Colon,
+    String,
+    Number,
+    Bool,
+    Null,
+    /// Input which is not json, like an unterminated string or `nul`.
+    Error,
+}
+
+impl TokenKind {
+    pub fn is_trivia(self) -> bool {
+        self == TokenKind::Whitespace || self == TokenKind::Comment
+    }
+}
+
+/// A token of a json text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    /// Range of the token including the quotes of strings.
+    pub range: Range,
+    /// The source of the token.
+    pub text: &'a str,
+}
+
+/// Move `pos` behind `text`.
+fn advance(pos: Position, text: &str) -> Position {
+    text.chars().fold(pos, |pos, c| {
+        if c == '\n' {
+            Position::new(pos.line + 1, 0, pos.idx + 1)
+        } else {
+            Position::new(pos.line, pos.char + 1, pos.idx + c.len_utf8())
+        }
+    })
+}
+
+fn token_kind(token: &TokenType) -> TokenKind {
+    match token {
+        TokenType::String(_, _) => TokenKind::String,
+        TokenType::Float(_, _) | TokenType::Int(_, _) => TokenKind::Number,
+        TokenType::Bool(_, _) => TokenKind::Bool,
+        TokenType::Null(_) => TokenKind::Null,
+        TokenType::ObjectOpen(_) => TokenKind::ObjectOpen,
+        TokenType::ObjectClose(_) => TokenKind::ObjectClose,
+        TokenType::ArrayOpen(_) => TokenKind::ArrayOpen,
+        TokenType::ArrayClose(_) => TokenKind::ArrayClose,
+        TokenType::Comma(_) => TokenKind::Comma,
+        TokenType::Colon(_) => TokenKind::Colon,
+        TokenType::Comment(_) => TokenKind::Comment,
+        TokenType::Invalid(_, _) => TokenKind::Error,
+    }
+}
+
+/// Whether the string with the content `range` ends with a closing quote. A
+/// tolerant tokenizer closes unterminated strings at the end of their line.
+pub(crate) fn has_closing_quote(text: &str, range: Range) -> bool {
+    text[range.end.idx..].starts_with('"')
+}
+
+fn source_range(text: &str, token: &TokenType) -> Range {
+    let range = token.range();
+    match token {
+        // The range of a string token is without its quotes.
+        TokenType::String(_, _) => Range::new(
+            Position::new(range.start.line, range.start.char - 1, range.start.idx - 1),
+            if has_closing_quote(text, range) {
+                range.end + Position::new(0, 1, 1)
+            } else {
+                range.end
+            },
+        ),
+        _ => range,
+    }
+}
+
+/// An iterator over the tokens of a text. The tokens cover every byte of the
+/// text, the gaps between json tokens are whitespace tokens.
+pub struct Lexer<'a> {
+    text: &'a str,
+    tokens: Peekable<IntoIter<TokenType>>,
+    position: Position,
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn with_tokens(text: &'a str, tokens: Vec<TokenType>) -> Lexer<'a> {
+        Lexer {
+            text,
+            tokens: tokens.into_iter().peekable(),
+            position: Position::default(),
+        }
+    }
+
+    fn token(&mut self, kind: TokenKind, end: usize) -> Token<'a> {
+        let text = &self.text[self.position.idx..end];
+        let range = Range::new(self.position, advance(self.position, text));
+        self.position = range.end;
+        Token { kind, range, text }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let text = self.text;
+        let next = self
+            .tokens
+            .peek()
+            .map(|token| (token_kind(token), source_range(text, token)));
+
+        match next {
+            Some((_, range)) if self.position.idx < range.start.idx => {
+                Some(self.token(TokenKind::Whitespace, range.start.idx))
+            }
+            Some((kind, range)) => {
+                self.tokens.next();
+                let text = &self.text[range.start.idx..range.end.idx];
+                self.position = range.end;
+                Some(Token { kind, range, text })
+            }
+            None if self.position.idx < self.text.len() => {
+                Some(self.token(TokenKind::Whitespace, self.text.len()))
+            }
+            None => None,
+        }
+    }
+}
+
+/// Split a json text into tokens, including whitespace and comments. Invalid
+/// input becomes `TokenKind::Error` tokens, so lexing never fails.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::lex::{lex, TokenKind};
+/// let tokens: Vec<(TokenKind, &str)> = lex("{\"a\": nul}")
+///     .map(|token| (token.kind, token.text))
+///     .collect();
+/// assert_eq!(
+///     vec![
+///         (TokenKind::ObjectOpen, "{"),
+///         (TokenKind::String, "\"a\""),
+///         (TokenKind::Colon, ":"),
+///         (TokenKind::Whitespace, " "),
+///         (TokenKind::Error, "nul"),
+///         (TokenKind::ObjectClose, "}"),
+///     ],
+///     tokens
+/// );
+/// ```
+pub fn lex(text: &str) -> Lexer<'_> {
+    Lexer::with_tokens(text, tokenize::tokenize_with_errors(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lex, TokenKind};
+
+    #[test]
+    fn test_lex() {
+        let text = "{\n  // é\n  \"ü\": [1.5, true, null],\n  \"b\": \"open\n}";
+        let tokens: Vec<(TokenKind, &str, usize, usize)> = lex(text)
+            .map(|t| (t.kind, t.text, t.range.start.line, t.range.start.char))
+            .collect();
+        assert_eq!(
+            vec![
+                (TokenKind::ObjectOpen, "{", 0, 0),
+                (TokenKind::Whitespace, "\n  ", 0, 1),
+                (TokenKind::Comment, "// é", 1, 2),
+                (TokenKind::Whitespace, "\n  ", 1, 6),
+                (TokenKind::String, "\"ü\"", 2, 2),
+                (TokenKind::Colon, ":", 2, 5),
+                (TokenKind::Whitespace, " ", 2, 6),
+                (TokenKind::ArrayOpen, "[", 2, 7),
+                (TokenKind::Number, "1.5", 2, 8),
+                (TokenKind::Comma, ",", 2, 11),
+                (TokenKind::Whitespace, " ", 2, 12),
+                (TokenKind::Bool, "true", 2, 13),
+                (TokenKind::Comma, ",", 2, 17),
+                (TokenKind::Whitespace, " ", 2, 18),
+                (TokenKind::Null, "null", 2, 19),
+                (TokenKind::ArrayClose, "]", 2, 23),
+                (TokenKind::Comma, ",", 2, 24),
+                (TokenKind::Whitespace, "\n  ", 2, 25),
+                (TokenKind::String, "\"b\"", 3, 2),
+                (TokenKind::Colon, ":", 3, 5),
+                (TokenKind::Whitespace, " ", 3, 6),
+                (TokenKind::Error, "\"open", 3, 7),
+                (TokenKind::Whitespace, "\n", 3, 12),
+                (TokenKind::ObjectClose, "}", 4, 0),
+            ],
+            tokens
+        );
+        assert_eq!(text, lex(text).map(|t| t.text).collect::<String>());
+    }
+
+    #[test]
+    fn test_lex_errors() {
+        for text in &[
+            "",
+            "  ",
+            "x",
+            "{ \"a",
+            "{ 1e }",
+            "{ / }",
+            "\"\u{e9}",
+            "@@ \"a\" ##",
+        ] {
+            let tokens: Vec<_> = lex(text).collect();
+            assert_eq!(*text, tokens.iter().map(|t| t.text).collect::<String>());
+            assert!(tokens
+                .windows(2)
+                .all(|w| w[0].range.end == w[1].range.start));
+        }
+
+        let kinds: Vec<TokenKind> = lex("@@ \"a\" ##").map(|t| t.kind).collect();
+        assert_eq!(
+            vec![
+                TokenKind::Error,
+                TokenKind::Whitespace,
+                TokenKind::String,
+                TokenKind::Whitespace,
+                TokenKind::Error
+            ],
+            kinds
+        );
+    }
+}
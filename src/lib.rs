@@ -1,16 +1,48 @@
+#[cfg(feature = "schema")]
+pub mod complete;
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod diff;
+pub mod edit;
+pub mod editor;
+pub mod format;
+pub mod lex;
+pub mod lint;
+pub mod merge;
 mod parser;
+pub mod patch;
+pub mod refs;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod semantic;
 pub mod tree;
 pub mod types;
+#[cfg(feature = "serde_json")]
+pub mod value;
+pub mod visit;
+pub mod writer;
 use parser::{parse, tokenize};
 use std::fs;
+use cst::SyntaxNode;
 use tree::Tree;
 use tokenize::TokenType;
 use types::{ParseError, ParseResult};
 
+fn tokenize_without_comments(text: &str) -> ParseResult<Vec<TokenType>> {
+    tokenize::tokenize(text).map(|tokens| {
+        tokens
+            .into_iter()
+            .filter(|e| !matches!(e, TokenType::Comment(_)))
+            .collect()
+    })
+}
+
 /// Parse a json text:
 ///
 /// **Example**
 /// ```
+/// # use json_position_parser::parse_json;
 /// let text = "{ \"foo\": \"bar\" }";
 /// match parse_json(&text) {
 ///     Ok(tree) => println!("{:?}", tree),
@@ -18,26 +50,92 @@ use types::{ParseError, ParseResult};
 /// };
 /// ```
 pub fn parse_json(text: &str) -> ParseResult<Tree> {
-    tokenize::tokenize(&text)
-        .map(|tokens| tokens)
-        .and_then(|tokens| {
+    tokenize_without_comments(text).and_then(|tokens| parse::parse_json(&tokens))
+}
 
-            let filtered_tokens: Vec<TokenType> = tokens.into_iter().filter(|e| {
-                if let TokenType::Comment(_,_) = e {
-                    return false;
-                }
-        
-                true
-            }).collect();
-            
-            parse::parse_json(&filtered_tokens[..])
+/// Parse a json text holding exactly one value of any type, e.g. a json patch
+/// document. The value is at the pointer `/` of the tree.
+pub(crate) fn parse_json_value(text: &str) -> ParseResult<Tree> {
+    tokenize_without_comments(text).and_then(parse::parse_json_value)
+}
+
+/// Parse a json text containing several root objects, separated by whitespace
+/// or comments only (e.g. JSON lines). Without this, content after the first
+/// root object is reported as `ParseError::TrailingContent`.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::parse_json_documents;
+/// let text = "{ \"foo\": 1 }\n{ \"foo\": 2 }";
+/// match parse_json_documents(&text) {
+///     Ok(trees) => println!("{}", trees.len()),
+///     Err(e) => println!("{:?}", e),
+/// };
+/// ```
+pub fn parse_json_documents(text: &str) -> ParseResult<Vec<Tree>> {
+    tokenize_without_comments(text).and_then(|tokens| parse::parse_json_documents(&tokens))
+}
+
+/// Parse a json text which may be incomplete or broken, e.g. while it is
+/// typed in an editor. Broken literals and stray brackets are skipped and
+/// unclosed objects and arrays are closed at the end of the text, so this
+/// always gives a tree.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::parse_json_tolerant;
+/// # use json_position_parser::tree::PathType;
+/// let tree = parse_json_tolerant("{ \"a\": { \"b\": tru, \"c\": [1, 2");
+/// let res = tree.value_at(&[PathType::Object("a"), PathType::Object("c")]);
+/// assert_eq!(1, res.len());
+/// ```
+pub fn parse_json_tolerant(text: &str) -> Tree {
+    let tokens: Vec<TokenType> = tokenize::tokenize_tolerant(text)
+        .into_iter()
+        .filter(|e| !matches!(e, TokenType::Comment(_)))
+        .collect();
+    let unclosed = tokens
+        .iter()
+        .filter_map(|token| match token {
+            TokenType::String(range, _) if !lex::has_closing_quote(text, *range) => Some(*range),
+            _ => None,
         })
+        .collect();
+
+    let mut tree = parse::parse_json_tolerant(tokens, edit::position_at(text, text.len()));
+    tree.unclosed = unclosed;
+    tree
 }
 
-/// Parse a json file:
+/// Parse a json text into a lossless concrete syntax tree. It keeps
+/// whitespace and comments, so writing it out gives the text again.
 ///
 /// **Example**
 /// ```
+/// # use json_position_parser::parse_cst;
+/// let text = "{\n  // comment\n  \"foo\": \"bar\"\n}\n";
+/// match parse_cst(&text) {
+///     Ok(cst) => assert_eq!(text, cst.to_string()),
+///     Err(e) => println!("{:?}", e),
+/// };
+/// ```
+pub fn parse_cst(text: &str) -> ParseResult<SyntaxNode> {
+    let (comments, mut tokens): (Vec<_>, Vec<_>) = tokenize::tokenize(text)?
+        .into_iter()
+        .partition(|e| matches!(e, TokenType::Comment(_)));
+    parse::parse_json(&tokens)?;
+
+    // Validated, put the comments back in place for the lossless tree.
+    tokens.extend(comments);
+    tokens.sort_by_key(|token| token.range().start.idx);
+    Ok(cst::build(text, tokens))
+}
+
+/// Parse a json file:
+///
+/// **Example**
+/// ```no_run
+/// # use json_position_parser::parse_json_file;
 /// let file_name = "foo.json";
 /// match parse_json_file(&file_name) {
 ///     Ok(tree) => println!("{:?}", tree),
@@ -54,6 +152,7 @@ pub fn parse_json_file(file_path: &str) -> ParseResult<Tree> {
 #[cfg(test)]
 mod tests {
     use super::tree::{EntryType, PathType};
+    use super::types::ParseError;
     #[test]
     fn test_parse() {
         let json = "// hulu \n {\"a/b\": {},\n//haha\n \"a\": {}, \"b\": { \"c\": [true, { \"e\": 42 } ] } }\n // hello";
@@ -115,4 +214,118 @@ mod tests {
             Err(_) => panic!("Could not parse json."),
         }
     }
+
+    #[test]
+    fn test_trailing_content() {
+        match super::parse_json("{ \"a\": true }\n xyz") {
+            Err(ParseError::TrailingContent(range)) => {
+                assert_eq!((1, 1), (range.start.line, range.start.char));
+                assert_eq!((1, 4), (range.end.line, range.end.char));
+            }
+            res => panic!("Should be trailing content, got '{:?}'", res),
+        }
+
+        match super::parse_json("{ \"a\": 1 }\n{ \"a\": 2 }") {
+            Err(ParseError::TrailingContent(_)) => {}
+            res => panic!("Should be trailing content, got '{:?}'", res),
+        }
+
+        match super::parse_json_documents("{ \"a\": 1 }\n// two\n{ \"a\": 2 }\n") {
+            Ok(trees) => {
+                assert_eq!(2, trees.len());
+                let res = trees[1].value_at(&[PathType::Object("a")]);
+                match res.first().unwrap().entry_type {
+                    EntryType::Int(val) => assert_eq!(2, val),
+                    _ => panic!("Should be number"),
+                }
+            }
+            Err(e) => panic!("Could not parse json documents: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_path_query() {
+        use super::tree::{PathQuery, QuerySegment};
+
+        let query = PathQuery::parse("$.a['b c'][2]..d[*].*").unwrap();
+        assert_eq!(
+            vec![
+                QuerySegment::Key("a".to_owned()),
+                QuerySegment::Key("b c".to_owned()),
+                QuerySegment::Index(2),
+                QuerySegment::RecursiveWildcard,
+                QuerySegment::Key("d".to_owned()),
+                QuerySegment::Wildcard,
+                QuerySegment::Wildcard,
+            ],
+            query.0
+        );
+        assert_eq!(
+            vec![QuerySegment::Key("a".to_owned())],
+            PathQuery::parse("a").unwrap().0
+        );
+        assert!(PathQuery::parse("$").unwrap().0.is_empty());
+
+        for invalid in &["$a", "$.", "$.a[x]", "$['a]", "$..", "$.a]"] {
+            match PathQuery::parse(invalid) {
+                Err(ParseError::InvalidPath(_)) => {}
+                res => panic!("Should be invalid path '{}', got '{:?}'", invalid, res),
+            }
+        }
+    }
+
+    #[test]
+    fn test_iter() {
+        let tree = super::parse_json("{ \"a\": { \"b\": [true, 1] }, \"c\": \"d\" }").unwrap();
+        let entries: Vec<(String, usize)> = tree
+            .iter()
+            .map(|(path, entry)| (path.to_string(), entry.range.start.idx))
+            .collect();
+        assert_eq!(
+            vec![
+                ("$".to_owned(), 0),
+                ("$.a".to_owned(), 7),
+                ("$.a.b".to_owned(), 14),
+                ("$.a.b[0]".to_owned(), 15),
+                ("$.a.b[1]".to_owned(), 21),
+                ("$.c".to_owned(), 33),
+            ],
+            entries
+        );
+
+        let b = tree.entry_at_pointer("/a/b").unwrap();
+        let paths: Vec<String> = tree
+            .iter_from(b)
+            .map(|(path, _)| path.to_string())
+            .collect();
+        assert_eq!(vec!["$.a.b", "$.a.b[0]", "$.a.b[1]"], paths);
+        assert_eq!(
+            3,
+            (&tree)
+                .into_iter()
+                .filter(|(path, _)| path.0.len() > 1)
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_parent() {
+        let tree = super::parse_json("{ \"a\": [1, { \"b\": 2 }], \"a\": [3] }").unwrap();
+        let b = tree.entry_at_pointer("/a/0").unwrap();
+        let parent = tree.parent(b).unwrap();
+        assert_eq!(Some(tree.entries.len() - 2), b.parent);
+        assert_eq!(tree.index_at_pointer("/a"), b.parent);
+        assert_eq!(tree.index_at_pointer(""), parent.parent);
+        assert!(tree.parent(tree.root().unwrap()).is_none());
+        assert_eq!("$.a[0]", tree.path_of(b).to_string());
+
+        // Values replaced by a later duplicate key have no parent.
+        let replaced = tree
+            .entries
+            .iter()
+            .find(|e| e.range.start.idx == 7)
+            .unwrap();
+        assert!(replaced.parent.is_none());
+        assert!(tree.index_at_pointer("/a/1").is_none());
+    }
 }
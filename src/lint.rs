@@ -0,0 +1,471 @@
+use super::parse_json;
+use super::tree::{Entry, EntryType, JsonPath, Key, PathQuery, Tree};
+use super::types::{Diagnostic, ParseResult, Range, Severity, TextEdit};
+use super::visit::{Visit, Visitor};
+use super::writer::quote_string;
+use std::collections::HashMap;
+
+/// A problem found by a rule, with edits fixing it if the rule knows how.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintDiagnostic {
+    pub diagnostic: Diagnostic,
+    pub fixes: Vec<TextEdit>,
+}
+
+/// What a rule sees while checking a tree. Rules report problems through it.
+pub struct LintContext<'a> {
+    pub tree: &'a Tree,
+    pub text: &'a str,
+    rule: &'static str,
+    severity: Severity,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl<'a> LintContext<'a> {
+    /// Report a problem of the current rule.
+    pub fn report(&mut self, range: Range, message: &str, fixes: Vec<TextEdit>) {
+        self.diagnostics.push(LintDiagnostic {
+            diagnostic: Diagnostic::new(range, self.severity, message, self.rule),
+            fixes,
+        });
+    }
+}
+
+/// A lint rule. The linter calls `check_tree` once, then walks the tree in
+/// source order, calling `check_key` for every object member and
+/// `check_entry` for every value. `depth` is the number of objects and arrays
+/// around the entry.
+pub trait Rule {
+    /// The name of the rule, reported as code of its diagnostics.
+    fn name(&self) -> &'static str;
+
+    fn check_tree(&self, _ctx: &mut LintContext) {}
+
+    fn check_key(&self, _ctx: &mut LintContext, _key: &Key, _path: &JsonPath) {}
+
+    fn check_entry(&self, _ctx: &mut LintContext, _entry: &Entry, _path: &JsonPath, _depth: usize) {
+    }
+}
+
+/// Reports keys which appear more than once in an object. Only the last value
+/// of a duplicate key is kept in the tree.
+pub struct NoDuplicateKeys;
+
+impl Rule for NoDuplicateKeys {
+    fn name(&self) -> &'static str {
+        "no-duplicate-keys"
+    }
+
+    fn check_tree(&self, ctx: &mut LintContext) {
+        let tree = ctx.tree;
+        let mut objects: Vec<&Entry> = tree
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry.entry_type, EntryType::JSONObject(_)))
+            .collect();
+        objects.sort_by_key(|entry| entry.range.start.idx);
+        let mut objects = objects.into_iter().peekable();
+
+        // Keys are in source order. Walking them along with the objects, the
+        // innermost object still open at a key is the one it belongs to. The
+        // hash of an object only has the last of duplicate keys, so it can
+        // not tell.
+        let mut open: Vec<&Entry> = vec![];
+        let mut first: HashMap<(Option<usize>, &str), &Key> = HashMap::new();
+        tree.keys.iter().for_each(|key| {
+            let idx = key.range.start.idx;
+            while let Some(object) = objects.next_if(|object| object.range.start.idx < idx) {
+                open.push(object);
+            }
+            while open
+                .last()
+                .is_some_and(|object| object.range.end.idx <= idx)
+            {
+                open.pop();
+            }
+            let owner = open.last().map(|object| object.range.start.idx);
+
+            match first.get(&(owner, key.name.as_str())) {
+                Some(first) => ctx.report(
+                    key.source_range(),
+                    &format!(
+                        "Duplicate key \"{}\", first defined at {}.",
+                        key.name,
+                        first.source_range().start
+                    ),
+                    vec![],
+                ),
+                None => {
+                    first.insert((owner, key.name.as_str()), key);
+                }
+            }
+        });
+    }
+}
+
+/// Reports keys which are not camelCase, e.g. `max_size`. Keys starting with
+/// `$`, like `$schema`, are allowed, as are the keys in `allow`.
+#[derive(Default)]
+pub struct CamelCaseKeys {
+    pub allow: Vec<String>,
+}
+
+fn to_camel_case(name: &str) -> String {
+    name.split(|c: char| c == '_' || c == '-' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .enumerate()
+        .map(|(idx, word)| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap_or_default();
+            let first: String = if idx == 0 {
+                first.to_lowercase().collect()
+            } else {
+                first.to_uppercase().collect()
+            };
+            first + chars.as_str()
+        })
+        .collect()
+}
+
+impl Rule for CamelCaseKeys {
+    fn name(&self) -> &'static str {
+        "camel-case-keys"
+    }
+
+    fn check_key(&self, ctx: &mut LintContext, key: &Key, _path: &JsonPath) {
+        if key.name.starts_with('$') || self.allow.contains(&key.name) {
+            return;
+        }
+
+        let camel_case = to_camel_case(&key.name);
+        if camel_case != key.name && !camel_case.is_empty() {
+            ctx.report(
+                key.source_range(),
+                &format!("Key \"{}\" is not camelCase.", key.name),
+                vec![TextEdit::new(
+                    key.source_range(),
+                    &quote_string(&camel_case),
+                )],
+            );
+        }
+    }
+}
+
+/// Reports empty objects. The fix removes them.
+pub struct NoEmptyObjects;
+
+impl Rule for NoEmptyObjects {
+    fn name(&self) -> &'static str {
+        "no-empty-objects"
+    }
+
+    fn check_entry(&self, ctx: &mut LintContext, entry: &Entry, path: &JsonPath, _depth: usize) {
+        match &entry.entry_type {
+            EntryType::JSONObject(hash) if hash.is_empty() && !path.0.is_empty() => {
+                let fixes = ctx
+                    .tree
+                    .remove_edits(ctx.text, &path.as_path_types())
+                    .unwrap_or_default();
+                ctx.report(entry.range, "Object is empty.", fixes);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reports objects and arrays nested deeper than `max` levels.
+pub struct MaxDepth {
+    pub max: usize,
+}
+
+impl Rule for MaxDepth {
+    fn name(&self) -> &'static str {
+        "max-depth"
+    }
+
+    fn check_entry(&self, ctx: &mut LintContext, entry: &Entry, _path: &JsonPath, depth: usize) {
+        let is_container = matches!(
+            entry.entry_type,
+            EntryType::JSONObject(_) | EntryType::JSONArray(_)
+        );
+        // Only the outermost container which is too deep is reported.
+        if is_container && depth == self.max {
+            ctx.report(
+                entry.range,
+                &format!("Nesting is deeper than {} levels.", self.max),
+                vec![],
+            );
+        }
+    }
+}
+
+/// Reports objects at `paths` whose keys are not sorted.
+pub struct SortedKeys {
+    pub paths: Vec<PathQuery>,
+}
+
+impl Rule for SortedKeys {
+    fn name(&self) -> &'static str {
+        "sorted-keys"
+    }
+
+    fn check_entry(&self, ctx: &mut LintContext, entry: &Entry, path: &JsonPath, _depth: usize) {
+        if !self.paths.iter().any(|query| query.matches(path)) {
+            return;
+        }
+
+        let members = ctx.tree.object_entries(entry);
+        let unsorted = members
+            .windows(2)
+            .find(|pair| pair[1].0.name < pair[0].0.name);
+        if let Some(pair) = unsorted {
+            let (previous, key) = (pair[0].0, pair[1].0);
+            ctx.report(
+                key.source_range(),
+                &format!(
+                    "Key \"{}\" should be before \"{}\".",
+                    key.name, previous.name
+                ),
+                vec![],
+            );
+        }
+    }
+}
+
+/// Reports floats at `paths`, where integers are expected. Floats without a
+/// fraction, like `8080.0`, are fixed.
+pub struct NoFloats {
+    pub paths: Vec<PathQuery>,
+}
+
+impl Rule for NoFloats {
+    fn name(&self) -> &'static str {
+        "no-floats"
+    }
+
+    fn check_entry(&self, ctx: &mut LintContext, entry: &Entry, path: &JsonPath, _depth: usize) {
+        if let EntryType::Float(value) = entry.entry_type {
+            if self.paths.iter().any(|query| query.matches(path)) {
+                let fixes = if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+                    vec![TextEdit::new(entry.range, &(value as i64).to_string())]
+                } else {
+                    vec![]
+                };
+                ctx.report(
+                    entry.range,
+                    &format!("Expected an integer but found {}.", value),
+                    fixes,
+                );
+            }
+        }
+    }
+}
+
+/// Checks trees with a set of rules, each with its own severity.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::lint::{CamelCaseKeys, Linter};
+/// # use json_position_parser::types::{Severity, TextEdit};
+/// let text = "{ \"max_size\": 1 }";
+/// let linter = Linter::new().with_rule(CamelCaseKeys::default(), Severity::Warning);
+/// let diagnostics = linter.lint(text).unwrap();
+/// assert_eq!("camel-case-keys", diagnostics[0].diagnostic.code);
+/// assert_eq!("{ \"maxSize\": 1 }", TextEdit::apply(text, &diagnostics[0].fixes));
+/// ```
+#[derive(Default)]
+pub struct Linter {
+    rules: Vec<(Box<dyn Rule>, Severity)>,
+}
+
+impl Linter {
+    /// A linter without rules.
+    pub fn new() -> Linter {
+        Linter::default()
+    }
+
+    /// A linter with the rules which are useful for any json file.
+    pub fn recommended() -> Linter {
+        Linter::new()
+            .with_rule(NoDuplicateKeys, Severity::Error)
+            .with_rule(NoEmptyObjects, Severity::Warning)
+            .with_rule(MaxDepth { max: 16 }, Severity::Warning)
+    }
+
+    pub fn with_rule<R: Rule + 'static>(mut self, rule: R, severity: Severity) -> Linter {
+        self.rules.push((Box::new(rule), severity));
+        self
+    }
+
+    /// Check a tree parsed from `text`. The diagnostics are sorted by position.
+    pub fn lint_tree(&self, tree: &Tree, text: &str) -> Vec<LintDiagnostic> {
+        let mut ctx = LintContext {
+            tree,
+            text,
+            rule: "",
+            severity: Severity::Error,
+            diagnostics: vec![],
+        };
+
+        self.rules.iter().for_each(|(rule, severity)| {
+            ctx.rule = rule.name();
+            ctx.severity = *severity;
+            rule.check_tree(&mut ctx);
+        });
+        let mut visitor = LintVisitor { linter: self, ctx };
+        tree.walk(&mut visitor);
+
+        let mut diagnostics = visitor.ctx.diagnostics;
+        diagnostics.sort_by_key(|d| d.diagnostic.range.start.idx);
+        diagnostics
+    }
+
+    /// Parse and check a json text.
+    pub fn lint(&self, text: &str) -> ParseResult<Vec<LintDiagnostic>> {
+        parse_json(text).map(|tree| self.lint_tree(&tree, text))
+    }
+}
+
+/// Runs the rules of a linter while walking a tree.
+struct LintVisitor<'l, 'a> {
+    linter: &'l Linter,
+    ctx: LintContext<'a>,
+}
+
+impl<'l, 'a> LintVisitor<'l, 'a> {
+    fn check_entry(&mut self, entry: &Entry, path: &JsonPath, depth: usize) -> Visit {
+        self.linter.rules.iter().for_each(|(rule, severity)| {
+            self.ctx.rule = rule.name();
+            self.ctx.severity = *severity;
+            rule.check_entry(&mut self.ctx, entry, path, depth);
+        });
+        Visit::Continue
+    }
+}
+
+impl<'l, 'a> Visitor for LintVisitor<'l, 'a> {
+    fn enter_object(&mut self, entry: &Entry, path: &JsonPath, depth: usize, _: Range) -> Visit {
+        self.check_entry(entry, path, depth)
+    }
+
+    fn enter_array(&mut self, entry: &Entry, path: &JsonPath, depth: usize, _: Range) -> Visit {
+        self.check_entry(entry, path, depth)
+    }
+
+    fn visit_key(&mut self, key: &Key, path: &JsonPath, _: usize, _: Range) -> Visit {
+        self.linter.rules.iter().for_each(|(rule, severity)| {
+            self.ctx.rule = rule.name();
+            self.ctx.severity = *severity;
+            rule.check_key(&mut self.ctx, key, path);
+        });
+        Visit::Continue
+    }
+
+    fn visit_value(&mut self, entry: &Entry, path: &JsonPath, depth: usize, _: Range) -> Visit {
+        self.check_entry(entry, path, depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CamelCaseKeys, Linter, MaxDepth, NoDuplicateKeys, NoFloats, SortedKeys};
+    use crate::tree::PathQuery;
+    use crate::types::{Position, Severity, TextEdit};
+
+    fn codes(linter: &Linter, text: &str) -> Vec<String> {
+        linter
+            .lint(text)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.diagnostic.code)
+            .collect()
+    }
+
+    #[test]
+    fn test_recommended() {
+        let text =
+            "{\n  \"a\": { \"b\": 1, \"b\": 2 },\n  \"b\": 3,\n  \"c\": {},\n  \"d\": [1]\n}";
+        let linter = Linter::recommended();
+        let diagnostics = linter.lint(text).unwrap();
+
+        assert_eq!(2, diagnostics.len());
+        assert_eq!("no-duplicate-keys", diagnostics[0].diagnostic.code);
+        assert_eq!(
+            "Duplicate key \"b\", first defined at 2:10.",
+            diagnostics[0].diagnostic.message
+        );
+        assert_eq!(
+            Position::new(1, 17, 19),
+            diagnostics[0].diagnostic.range.start
+        );
+        assert_eq!(Severity::Error, diagnostics[0].diagnostic.severity);
+
+        assert_eq!("no-empty-objects", diagnostics[1].diagnostic.code);
+        assert_eq!(Severity::Warning, diagnostics[1].diagnostic.severity);
+        assert_eq!(
+            "{\n  \"a\": { \"b\": 1, \"b\": 2 },\n  \"b\": 3,\n  \"d\": [1]\n}",
+            TextEdit::apply(text, &diagnostics[1].fixes)
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let linter = Linter::new().with_rule(NoDuplicateKeys, Severity::Error);
+        // Keys of sibling and nested objects are apart, a key after a nested
+        // object belongs to the outer one again.
+        let text = "{ \"a\": { \"x\": 1 }, \"b\": { \"x\": { \"x\": 2 }, \"y\": 3 }, \"x\": [{ \"a\": 1 }] }";
+        assert!(codes(&linter, text).is_empty());
+
+        let text = "{ \"a\": 1, \"b\": { \"a\": 2 }, \"a\": 3, \"a\": 4 }";
+        let messages: Vec<String> = linter
+            .lint(text)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.diagnostic.message)
+            .collect();
+        assert_eq!(
+            vec![
+                "Duplicate key \"a\", first defined at 1:3.",
+                "Duplicate key \"a\", first defined at 1:3.",
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_configured_rules() {
+        let linter = Linter::new()
+            .with_rule(
+                CamelCaseKeys {
+                    allow: vec!["ID".to_owned()],
+                },
+                Severity::Warning,
+            )
+            .with_rule(MaxDepth { max: 2 }, Severity::Warning)
+            .with_rule(
+                SortedKeys {
+                    paths: vec![PathQuery::parse("$.deps").unwrap()],
+                },
+                Severity::Hint,
+            )
+            .with_rule(
+                NoFloats {
+                    paths: vec![PathQuery::parse("$..port").unwrap()],
+                },
+                Severity::Error,
+            );
+
+        let text = "{ \"ID\": 1, \"$schema\": \"x\", \"user-name\": \"a\", \"deps\": { \"b\": 1, \"a\": 2 }, \"x\": { \"y\": [[1]] }, \"s\": { \"port\": 80.0 } }";
+        assert_eq!(
+            vec!["camel-case-keys", "sorted-keys", "max-depth", "no-floats"],
+            codes(&linter, text)
+        );
+
+        let diagnostics = linter.lint(text).unwrap();
+        let fixes: Vec<TextEdit> = diagnostics.into_iter().flat_map(|d| d.fixes).collect();
+        assert_eq!(
+            "{ \"ID\": 1, \"$schema\": \"x\", \"userName\": \"a\", \"deps\": { \"b\": 1, \"a\": 2 }, \"x\": { \"y\": [[1]] }, \"s\": { \"port\": 80 } }",
+            TextEdit::apply(text, &fixes)
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use super::format::{format, FormatOptions};
+use super::parse_json;
+use super::tree::{Entry, EntryType, JsonPath, Tree};
+use super::types::{ParseResult, Range};
+use super::writer::{quote_string, write_entry, WriteOptions};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Where a value of a merged document came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    /// The name of the source, e.g. its file name.
+    pub source: String,
+    /// The range of the value in the source.
+    pub range: Range,
+}
+
+/// A value of the merged document, with the index of the source it is from.
+enum Node<'a> {
+    Object(Vec<(String, Node<'a>)>, usize, Range),
+    Value(&'a Tree, &'a Entry, usize),
+}
+
+impl<'a> Node<'a> {
+    fn new(tree: &'a Tree, entry: &'a Entry, source: usize) -> Node<'a> {
+        match entry.entry_type {
+            EntryType::JSONObject(_) => Node::Object(
+                tree.object_entries(entry)
+                    .into_iter()
+                    .map(|(key, value)| (key.name.clone(), Node::new(tree, value, source)))
+                    .collect(),
+                source,
+                entry.source_range(),
+            ),
+            _ => Node::Value(tree, entry, source),
+        }
+    }
+
+    /// Apply a merge patch to an optional target value.
+    fn merge(
+        target: Option<Node<'a>>,
+        tree: &'a Tree,
+        patch: &'a Entry,
+        source: usize,
+    ) -> Node<'a> {
+        if !matches!(patch.entry_type, EntryType::JSONObject(_)) {
+            return Node::Value(tree, patch, source);
+        }
+
+        let (mut members, object_source, range) = match target {
+            Some(Node::Object(members, object_source, range)) => (members, object_source, range),
+            _ => (vec![], source, patch.source_range()),
+        };
+        tree.object_entries(patch)
+            .into_iter()
+            .for_each(|(key, value)| {
+                let pos = members.iter().position(|(name, _)| *name == key.name);
+                match (pos, &value.entry_type) {
+                    (Some(pos), EntryType::Null) => {
+                        members.remove(pos);
+                    }
+                    (None, EntryType::Null) => {}
+                    (Some(pos), _) => {
+                        let (name, existing) = members.remove(pos);
+                        members.insert(
+                            pos,
+                            (name, Node::merge(Some(existing), tree, value, source)),
+                        );
+                    }
+                    (None, _) => {
+                        members.push((key.name.clone(), Node::merge(None, tree, value, source)))
+                    }
+                }
+            });
+        Node::Object(members, object_source, range)
+    }
+
+    fn write(
+        &self,
+        path: JsonPath,
+        names: &[&str],
+        out: &mut String,
+        origins: &mut HashMap<JsonPath, Origin>,
+    ) {
+        let (source, range) = match self {
+            Node::Object(members, source, range) => {
+                out.push('{');
+                members.iter().enumerate().for_each(|(idx, (key, value))| {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    let _ = write!(out, "{}:", quote_string(key));
+                    value.write(path.with_key(key), names, out, origins);
+                });
+                out.push('}');
+                (*source, *range)
+            }
+            Node::Value(tree, entry, source) => {
+                out.push_str(&write_entry(tree, entry, &WriteOptions::compact()));
+                record(tree, entry, &path, names[*source], origins);
+                (*source, entry.source_range())
+            }
+        };
+        let origin = Origin {
+            source: names[source].to_owned(),
+            range,
+        };
+        origins.insert(path, origin);
+    }
+}
+
+/// Record the origins of the values inside of a value taken from a source.
+fn record(
+    tree: &Tree,
+    entry: &Entry,
+    path: &JsonPath,
+    name: &str,
+    origins: &mut HashMap<JsonPath, Origin>,
+) {
+    let children: Vec<(JsonPath, &Entry)> = match entry.entry_type {
+        EntryType::JSONObject(_) => tree
+            .object_entries(entry)
+            .into_iter()
+            .map(|(key, value)| (path.with_key(&key.name), value))
+            .collect(),
+        EntryType::JSONArray(_) => tree
+            .array_entries(entry)
+            .into_iter()
+            .enumerate()
+            .map(|(idx, value)| (path.with_index(idx), value))
+            .collect(),
+        _ => vec![],
+    };
+    children.into_iter().for_each(|(path, value)| {
+        record(tree, value, &path, name, origins);
+        let origin = Origin {
+            source: name.to_owned(),
+            range: value.source_range(),
+        };
+        origins.insert(path, origin);
+    });
+}
+
+/// The result of merging documents, with the origin of every value.
+pub struct Merged {
+    pub text: String,
+    pub tree: Tree,
+    origins: HashMap<JsonPath, Origin>,
+}
+
+impl Merged {
+    /// Get where the value at `path` came from. Objects keep the origin of
+    /// the source which created them, even if later sources added members.
+    pub fn origin(&self, path: &JsonPath) -> Option<&Origin> {
+        self.origins.get(path)
+    }
+
+    /// Get where an entry of the merged tree came from.
+    pub fn origin_of(&self, entry: &Entry) -> Option<&Origin> {
+        self.origin(&self.tree.path_of(entry))
+    }
+
+    /// List the origin of every value that is not an object, in document
+    /// order, e.g. `$.server.port prod.json:3:13`.
+    pub fn blame(&self) -> String {
+        let mut entries: Vec<&Entry> = self
+            .tree
+            .entries
+            .iter()
+            .filter(|entry| !matches!(entry.entry_type, EntryType::JSONObject(_)))
+            .filter(|entry| {
+                self.tree
+                    .parent(entry)
+                    .is_some_and(|parent| matches!(parent.entry_type, EntryType::JSONObject(_)))
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.range.start.idx);
+
+        entries.iter().fold(String::new(), |mut out, entry| {
+            let path = self.tree.path_of(entry);
+            if let Some(origin) = self.origin(&path) {
+                let _ = writeln!(out, "{} {}:{}", path, origin.source, origin.range.start);
+            }
+            out
+        })
+    }
+}
+
+/// Merge documents with json merge patch (RFC 7396). The first source is the
+/// base document, every following one is applied to it as merge patch: `null`
+/// removes a member, objects are merged and any other value replaces the
+/// previous one. Sources are named, e.g. by their file name.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::{merge::merge, parse_json, tree::JsonPath};
+/// let base = parse_json("{ \"port\": 80, \"debug\": true }").unwrap();
+/// let prod = parse_json("{\n  \"port\": 443,\n  \"debug\": null\n}").unwrap();
+/// let merged = merge(&[("base.json", &base), ("prod.json", &prod)]).unwrap();
+/// assert_eq!("{ \"port\": 443 }\n", merged.text);
+///
+/// let origin = merged.origin(&JsonPath::default().with_key("port")).unwrap();
+/// assert_eq!("prod.json", origin.source);
+/// assert_eq!(1, origin.range.start.line);
+/// ```
+pub fn merge(sources: &[(&str, &Tree)]) -> ParseResult<Merged> {
+    let names: Vec<&str> = sources.iter().map(|(name, _)| *name).collect();
+    let root = sources
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, (_, tree))| tree.root().map(|root| (idx, *tree, root)))
+        .fold(None, |target, (idx, tree, root)| match target {
+            None => Some(Node::new(tree, root, idx)),
+            target => Some(Node::merge(target, tree, root, idx)),
+        });
+
+    let mut compact = String::new();
+    let mut origins = HashMap::new();
+    match root {
+        Some(root) => root.write(JsonPath::default(), &names, &mut compact, &mut origins),
+        None => compact.push_str("{}"),
+    }
+
+    let text = format(&compact, &FormatOptions::default())?;
+    let tree = parse_json(&text)?;
+    Ok(Merged {
+        text,
+        tree,
+        origins,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge;
+    use crate::parse_json;
+    use crate::tree::JsonPath;
+
+    #[test]
+    fn test_merge() {
+        let base = parse_json(
+            "{\n  \"server\": { \"host\": \"localhost\", \"port\": 80 },\n  \"tags\": [\"a\", \"b\"],\n  \"debug\": true\n}",
+        )
+        .unwrap();
+        let prod = parse_json(
+            "{\n  \"server\": { \"host\": \"example.com\", \"tls\": { \"cert\": \"c.pem\" } },\n  \"tags\": [\"prod\"],\n  \"debug\": null\n}",
+        )
+        .unwrap();
+        let local = parse_json("{ \"server\": { \"tls\": null, \"port\": 8080 } }").unwrap();
+
+        let merged = merge(&[
+            ("base.json", &base),
+            ("prod.json", &prod),
+            ("local.json", &local),
+        ])
+        .unwrap();
+        assert_eq!(
+            "{ \"server\": { \"host\": \"example.com\", \"port\": 8080 }, \"tags\": [\"prod\"] }\n",
+            merged.text
+        );
+
+        let server = JsonPath::default().with_key("server");
+        let origin = merged.origin(&server).unwrap();
+        assert_eq!("base.json", origin.source);
+        assert_eq!(12, origin.range.start.char);
+        let origin = merged.origin(&server.with_key("port")).unwrap();
+        assert_eq!("local.json", origin.source);
+        assert_eq!(35, origin.range.start.char);
+        let tags = merged
+            .tree
+            .entry_at_pointer("/tags/0")
+            .and_then(|entry| merged.origin_of(entry))
+            .unwrap();
+        assert_eq!("prod.json", tags.source);
+        assert_eq!(2, tags.range.start.line);
+
+        assert_eq!(
+            "$.server.host prod.json:2:23\n$.server.port local.json:1:36\n$.tags prod.json:3:11\n",
+            merged.blame()
+        );
+    }
+
+    #[test]
+    fn test_merge_replaces_values() {
+        let base = parse_json("{ \"a\": [1], \"b\": { \"c\": 1 } }").unwrap();
+        let patch = parse_json("{ \"a\": { \"x\": null, \"y\": 2 }, \"b\": 3 }").unwrap();
+
+        let merged = merge(&[("base", &base), ("patch", &patch)]).unwrap();
+        assert_eq!("{ \"a\": { \"y\": 2 }, \"b\": 3 }\n", merged.text);
+        assert_eq!(
+            "patch",
+            merged
+                .origin(&JsonPath::default().with_key("a"))
+                .unwrap()
+                .source
+        );
+    }
+}
@@ -98,26 +98,31 @@ fn handle_primitives(token: &TokenType, key: Option<usize>) -> Option<Entry> {
             key,
             entry_type: EntryType::String(val.clone()),
             range: *range,
+            parent: None,
         }),
         TokenType::Float(range, val) => Some(Entry {
             key,
             entry_type: EntryType::Float(*val),
             range: *range,
+            parent: None,
         }),
         TokenType::Int(range, val) => Some(Entry {
             key,
             entry_type: EntryType::Int(*val),
             range: *range,
+            parent: None,
         }),
         TokenType::Bool(range, val) => Some(Entry {
             key,
             entry_type: EntryType::Bool(*val),
             range: *range,
+            parent: None,
         }),
         TokenType::Null(range) => Some(Entry {
             key,
             entry_type: EntryType::Null,
             range: *range,
+            parent: None,
         }),
         _ => None,
     }
@@ -131,7 +136,7 @@ fn handle_array(
     pre_calc: &HashMap<usize, usize>,
     pre_pos: usize,
 ) -> ArrayParseResult {
-    let (mut json_array, range) = create_new_array(&tokens);
+    let (mut json_array, range) = create_new_array(tokens);
     let mut skip = 0;
 
     tokens[1..(tokens.len() - 1)]
@@ -161,6 +166,7 @@ fn handle_array(
                                     key: None,
                                     range,
                                     entry_type: EntryType::JSONObject(hash),
+                                    parent: None,
                                 });
                                 json_array.push(tree.entries.len() - 1);
                             })
@@ -185,6 +191,7 @@ fn handle_array(
                                     key: None,
                                     range,
                                     entry_type: EntryType::JSONArray(array_vec),
+                                    parent: None,
                                 });
                                 json_array.push(tree.entries.len() - 1);
                             })
@@ -192,7 +199,7 @@ fn handle_array(
                         .ok_or(ParseError::MissingArrayBrace)
                 }
                 _ => {
-                    if let Some(primitive) = handle_primitives(&token, None) {
+                    if let Some(primitive) = handle_primitives(token, None) {
                         tree.entries.push(primitive);
                         json_array.push(tree.entries.len() - 1);
                     }
@@ -215,7 +222,7 @@ fn handle_object(
 ) -> ObjectParseResult {
     let mut is_key = true;
     let mut key_pos: usize = 0;
-    let (mut json_object, range) = create_new_object(&tokens);
+    let (mut json_object, range) = create_new_object(tokens);
     let mut skip = 0;
 
     tokens[1..(tokens.len() - 1)]
@@ -231,7 +238,7 @@ fn handle_object(
                     is_key = true;
                     Ok(())
                 }
-                TokenType::Semicolon(_) => {
+                TokenType::Colon(_) => {
                     is_key = false;
                     Ok(())
                 }
@@ -244,7 +251,7 @@ fn handle_object(
                         key_pos = tree.keys.len() - 1;
                         return Ok(());
                     }
-                    handle_primitives(&token, Some(key_pos))
+                    handle_primitives(token, Some(key_pos))
                         .and_then(|primitive| {
                             tree.keys
                                 .get(key_pos)
@@ -278,6 +285,7 @@ fn handle_object(
                                             key: Some(key_pos),
                                             range,
                                             entry_type: EntryType::JSONObject(hash),
+                                            parent: None,
                                         });
                                         json_object.insert(name, (key_pos, tree.entries.len() - 1));
 
@@ -309,6 +317,7 @@ fn handle_object(
                                             key: Some(key_pos),
                                             range,
                                             entry_type: EntryType::JSONArray(array_vec),
+                                            parent: None,
                                         });
                                         json_object.insert(name, (key_pos, tree.entries.len() - 1));
                                     })
@@ -317,7 +326,7 @@ fn handle_object(
                         .ok_or(ParseError::MissingArrayBrace)
                 }
                 _ => {
-                    handle_primitives(&token, Some(key_pos)).map(|primitive| {
+                    handle_primitives(token, Some(key_pos)).map(|primitive| {
                         tree.keys
                             .get(key_pos)
                             .map(|key| key.name.clone())
@@ -335,22 +344,210 @@ fn handle_object(
         .map(|_| (json_object, range))
 }
 
-pub fn parse_json(tokens: &[TokenType]) -> ParseResult<Tree> {
+/// Find the closing token of the root object starting at `start` and make sure
+/// it does not contain any invalid token.
+fn root_close(
+    tokens: &[TokenType],
+    pre_calc: &HashMap<usize, usize>,
+    start: usize,
+) -> ParseResult<usize> {
+    match tokens.get(start) {
+        Some(TokenType::ObjectOpen(_)) => {}
+        Some(TokenType::Invalid(range, _)) => return Err(ParseError::UnexpectedCharacter(*range)),
+        _ => return Err(ParseError::MissingObjectBrace),
+    }
+
+    let close = *pre_calc.get(&start).ok_or(ParseError::MissingObjectBrace)?;
+
+    if let Some(range) = tokens[start..=close].iter().find_map(|token| match token {
+        TokenType::Invalid(range, _) => Some(*range),
+        _ => None,
+    }) {
+        return Err(ParseError::UnexpectedCharacter(range));
+    }
+
+    Ok(close)
+}
+
+fn parse_root(
+    tokens: &[TokenType],
+    pre_calc: &HashMap<usize, usize>,
+    start: usize,
+    close: usize,
+) -> ParseResult<Tree> {
     let mut tree = Tree {
         entries: vec![],
         keys: vec![],
+        unclosed: vec![],
+        invalid: vec![],
     };
 
-    let pre_calc = pre_calculate_positions(&tokens);
-    pre_calc
-        .get(&0)
-        .ok_or(ParseError::MissingObjectBrace)?;
-    handle_object(&mut tree, &tokens, &pre_calc, 0).map(|(hash, range)| {
+    handle_object(&mut tree, &tokens[start..=close], pre_calc, start).map(|(hash, range)| {
         tree.entries.push(Entry {
             key: None,
             range,
             entry_type: EntryType::JSONObject(hash),
+            parent: None,
         });
+        tree.link_parents();
         tree
     })
 }
+
+pub fn parse_json(tokens: &[TokenType]) -> ParseResult<Tree> {
+    let pre_calc = pre_calculate_positions(tokens);
+    let close = root_close(tokens, &pre_calc, 0)?;
+
+    if let (Some(first), Some(last)) = (tokens.get(close + 1), tokens.last()) {
+        return Err(ParseError::TrailingContent(Range::new(
+            first.range().start,
+            last.range().end,
+        )));
+    }
+
+    parse_root(tokens, &pre_calc, 0, close)
+}
+
+/// Parse exactly one value of any type, e.g. an array document or a value
+/// given for an edit. The tree has a root object holding the value under the
+/// empty key, so the value is at the pointer `/`. Positions are those of the
+/// tokens.
+pub fn parse_json_value(mut tokens: Vec<TokenType>) -> ParseResult<Tree> {
+    let first = tokens.first().ok_or(ParseError::InvalidType)?;
+    let pre_calc = pre_calculate_positions(&tokens);
+    let close = match first {
+        TokenType::ObjectOpen(_) => *pre_calc.get(&0).ok_or(ParseError::MissingObjectBrace)?,
+        TokenType::ArrayOpen(_) => *pre_calc.get(&0).ok_or(ParseError::MissingArrayBrace)?,
+        TokenType::ObjectClose(range)
+        | TokenType::ArrayClose(range)
+        | TokenType::Comma(range)
+        | TokenType::Colon(range)
+        | TokenType::Invalid(range, _) => return Err(ParseError::UnexpectedCharacter(*range)),
+        _ => 0,
+    };
+
+    if let (Some(first), Some(last)) = (tokens.get(close + 1), tokens.last()) {
+        return Err(ParseError::TrailingContent(Range::new(
+            first.range().start,
+            last.range().end,
+        )));
+    }
+
+    let start = Range::new(first.range().start, first.range().start);
+    let end = Range::new(tokens[close].range().end, tokens[close].range().end);
+    tokens.splice(
+        0..0,
+        vec![
+            TokenType::ObjectOpen(start),
+            TokenType::String(start, String::new()),
+            TokenType::Colon(start),
+        ],
+    );
+    tokens.push(TokenType::ObjectClose(end));
+
+    parse_json(&tokens)
+}
+
+/// Parse several root objects following each other, e.g. JSON lines.
+pub fn parse_json_documents(tokens: &[TokenType]) -> ParseResult<Vec<Tree>> {
+    let pre_calc = pre_calculate_positions(tokens);
+    let mut trees = vec![];
+    let mut start = 0;
+
+    while start < tokens.len() || trees.is_empty() {
+        let close = root_close(tokens, &pre_calc, start)?;
+        trees.push(parse_root(tokens, &pre_calc, start, close)?);
+        start = close + 1;
+    }
+
+    Ok(trees)
+}
+
+fn closing_token(open: &TokenType, at: Position) -> TokenType {
+    let range = Range::new(at, at);
+    match open {
+        TokenType::ArrayOpen(_) => TokenType::ArrayClose(range),
+        _ => TokenType::ObjectClose(range),
+    }
+}
+
+/// Repair the tokens of half-typed input into a single root object. Invalid
+/// tokens, stray closing brackets and content outside of the root object are
+/// dropped. Brackets left open are closed with empty tokens where their
+/// parent closes, or at `end`.
+fn repair(tokens: Vec<TokenType>, end: Position) -> Vec<TokenType> {
+    let mut repaired = vec![];
+    let mut stack: Vec<usize> = vec![];
+    let mut tokens = tokens
+        .into_iter()
+        .filter(|token| !matches!(token, TokenType::Invalid(_, _)))
+        .skip_while(|token| !matches!(token, TokenType::ObjectOpen(_)));
+
+    for token in tokens.by_ref() {
+        match token {
+            TokenType::ObjectOpen(_) | TokenType::ArrayOpen(_) => {
+                stack.push(repaired.len());
+                repaired.push(token);
+            }
+            TokenType::ObjectClose(_) | TokenType::ArrayClose(_) => {
+                let is_object = matches!(token, TokenType::ObjectClose(_));
+                let matching = stack.iter().rposition(|open| {
+                    matches!(repaired[*open], TokenType::ObjectOpen(_)) == is_object
+                });
+                if let Some(matching) = matching {
+                    let at = token.range().start;
+                    while stack.len() > matching + 1 {
+                        let open = stack.pop().unwrap_or_default();
+                        let close = closing_token(&repaired[open], at);
+                        repaired.push(close);
+                    }
+                    stack.pop();
+                    repaired.push(token);
+                }
+            }
+            _ => repaired.push(token),
+        }
+
+        if stack.is_empty() {
+            break;
+        }
+    }
+
+    while let Some(open) = stack.pop() {
+        let close = closing_token(&repaired[open], end);
+        repaired.push(close);
+    }
+    repaired
+}
+
+/// Parse half-typed input, e.g. while it is edited. Errors are recovered
+/// from as far as possible, so this always gives a tree with a root object.
+pub fn parse_json_tolerant(tokens: Vec<TokenType>, end: Position) -> Tree {
+    let invalid = tokens
+        .iter()
+        .filter_map(|token| match token {
+            TokenType::Invalid(range, _) => Some(*range),
+            _ => None,
+        })
+        .collect();
+    let tokens = repair(tokens, end);
+    let pre_calc = pre_calculate_positions(&tokens);
+
+    let mut tree = match tokens.len() {
+        0 => None,
+        len => parse_root(&tokens, &pre_calc, 0, len - 1).ok(),
+    }
+    .unwrap_or_else(|| Tree {
+        entries: vec![Entry {
+            key: None,
+            range: Range::new(Position::default(), end),
+            entry_type: EntryType::JSONObject(HashMap::new()),
+            parent: None,
+        }],
+        keys: vec![],
+        unclosed: vec![],
+        invalid: vec![],
+    });
+    tree.invalid = invalid;
+    tree
+}
@@ -20,258 +20,426 @@ pub enum TokenType {
     ArrayOpen(Range),
     ArrayClose(Range),
     Comma(Range),
-    Semicolon(Range),
-    Comment(Range, String),
+    Colon(Range),
+    Comment(Range),
+    Invalid(Range, String),
+}
+
+impl TokenType {
+    pub fn range(&self) -> Range {
+        match self {
+            TokenType::String(range, _)
+            | TokenType::Float(range, _)
+            | TokenType::Int(range, _)
+            | TokenType::Bool(range, _)
+            | TokenType::Invalid(range, _) => *range,
+            TokenType::Null(range)
+            | TokenType::ObjectOpen(range)
+            | TokenType::ObjectClose(range)
+            | TokenType::ArrayOpen(range)
+            | TokenType::ArrayClose(range)
+            | TokenType::Comma(range)
+            | TokenType::Colon(range)
+            | TokenType::Comment(range) => *range,
+        }
+    }
 }
 
 fn handle_defaults(c: char, tokens: &mut Vec<TokenType>, pos: Position) {
+    let range = Range {
+        start: pos,
+        end: pos + Position::new(0, 1, c.len_utf8()),
+    };
+
     match c {
-        '{' => {
-            tokens.push(TokenType::ObjectOpen(Range {
-                start: pos,
-                end: pos + Position::new(0, 1, 1),
-            }));
+        '{' => tokens.push(TokenType::ObjectOpen(range)),
+        '}' => tokens.push(TokenType::ObjectClose(range)),
+        '[' => tokens.push(TokenType::ArrayOpen(range)),
+        ']' => tokens.push(TokenType::ArrayClose(range)),
+        ',' => tokens.push(TokenType::Comma(range)),
+        ':' => tokens.push(TokenType::Colon(range)),
+        ' ' | '\t' | '\r' | '\n' => {}
+        _ => {
+            // Merge runs of unknown characters into one token, so garbage like
+            // `xyz` is reported once with its full range.
+            if let Some(TokenType::Invalid(last, text)) = tokens.last_mut() {
+                if last.end.idx == pos.idx {
+                    last.end = range.end;
+                    text.push(c);
+                    return;
+                }
+            }
+            tokens.push(TokenType::Invalid(range, c.to_string()));
         }
-        '}' => {
-            tokens.push(TokenType::ObjectClose(Range {
-                start: pos,
-                end: pos + Position::new(0, 1, 1),
-            }));
+    };
+}
+
+fn is_number_char(c: char) -> bool {
+    c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e' || c == 'E'
+}
+
+/// Check a number literal against the json grammar: an optional minus, an
+/// integer part without leading zeros, an optional fraction with at least one
+/// digit and an optional exponent. This rejects literals like `01`, `1.`,
+/// `.5` or `+1` that rust's number parsing would accept.
+fn is_json_number(literal: &str) -> bool {
+    let bytes = literal.as_bytes();
+    let mut i = 0;
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
         }
-        '[' => {
-            tokens.push(TokenType::ArrayOpen(Range {
-                start: pos,
-                end: pos + Position::new(0, 1, 1),
-            }));
+        *i - start
+    };
+
+    if bytes.first() == Some(&b'-') {
+        i += 1;
+    }
+    match bytes.get(i) {
+        Some(b'0') => i += 1,
+        Some(b'1'..=b'9') => {
+            digits(&mut i);
         }
-        ']' => {
-            tokens.push(TokenType::ArrayClose(Range {
-                start: pos,
-                end: pos + Position::new(0, 1, 1),
-            }));
+        _ => return false,
+    }
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false;
         }
-        ',' => {
-            tokens.push(TokenType::Comma(Range {
-                start: pos,
-                end: pos + Position::new(0, 1, 1),
-            }));
+    }
+    if let Some(b'e' | b'E') = bytes.get(i) {
+        i += 1;
+        if let Some(b'+' | b'-') = bytes.get(i) {
+            i += 1;
         }
-        ':' => {
-            tokens.push(TokenType::Semicolon(Range {
-                start: pos,
-                end: pos + Position::new(0, 1, 1),
-            }));
+        if digits(&mut i) == 0 {
+            return false;
         }
-        _ => {}
-    };
+    }
+
+    i == bytes.len()
 }
 
-pub fn tokenize(string: &str) -> ParseResult<Vec<TokenType>> {
-    let mut tokens = vec![];
-    let mut current_type = None;
-    let mut current_type_start = Position::default();
-    let mut concat_string = String::new();
-    let mut current_line = 0;
-    let mut current_char = 0;
-    let mut escaped = false;
-
-    string
-        .chars()
-        .enumerate()
-        .map(|(pos, c)| {
-            if '\n' == c && pos != string.len() - 1 {
-                current_line += 1;
-                current_char = 0;
-                current_type = None;
-                concat_string = String::new();
-                return Ok(());
+/// Parse the four hex digits of a `\\uXXXX` escape.
+fn hex4(hex: &str) -> Option<u32> {
+    if hex.len() == 4 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        None
+    }
+}
+
+/// Decode the escape sequences of a json string, which gives string tokens
+/// their value rather than their source text. `\\uXXXX` escapes are decoded
+/// and utf-16 surrogate pairs are combined into one character. Unknown
+/// escapes, malformed hex digits and lone surrogates are kept as written.
+fn unescape(raw: &str) -> String {
+    let mut result = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('"') => result.push('"'),
+            Some('\\') => result.push('\\'),
+            Some('/') => result.push('/'),
+            Some('b') => result.push('\u{8}'),
+            Some('f') => result.push('\u{c}'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let mut code = hex4(&hex);
+
+                // Combine utf-16 surrogate pairs like `\ud83d\ude00`.
+                if let Some(high @ 0xD800..=0xDBFF) = code {
+                    let rest: String = chars.clone().take(6).collect();
+                    code = rest
+                        .strip_prefix("\\u")
+                        .and_then(hex4)
+                        .filter(|low| (0xDC00..=0xDFFF).contains(low))
+                        .map(|low| {
+                            chars.by_ref().take(6).for_each(drop);
+                            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                        });
+                }
+
+                match code.and_then(std::char::from_u32) {
+                    Some(decoded) => result.push(decoded),
+                    None => {
+                        result.push_str("\\u");
+                        result.push_str(&hex);
+                    }
+                }
+            }
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
             }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
 
-            if '\n' == c || pos == string.len() - 1 {
-                if let Some(CurrentTokenType::Comment) = current_type {
-                    let mut pos = pos;
-                    let mut current_char = current_char;
-                    if '\n' != c {
-                        concat_string.push(c);
-                        pos += 1;
-                        current_char += 1;
+#[derive(Default)]
+struct Tokenizer {
+    tokens: Vec<TokenType>,
+    current_type: Option<CurrentTokenType>,
+    current_type_start: Position,
+    concat_string: String,
+    escaped: bool,
+    tolerant: bool,
+    /// Keep unterminated strings as strings in tolerant mode.
+    close_strings: bool,
+    /// The first broken literal, which stops tokenizing unless tolerant.
+    error: Option<ParseError>,
+}
+
+impl Tokenizer {
+    fn reset(&mut self) {
+        self.current_type = None;
+        self.concat_string = String::new();
+        self.escaped = false;
+    }
+
+    fn start(&mut self, c: char, pos: Position) {
+        let current_type = match c {
+            '"' => CurrentTokenType::String,
+            'n' => CurrentTokenType::Null,
+            't' | 'f' => CurrentTokenType::Bool,
+            '-' | '0'..='9' => CurrentTokenType::Number,
+            '/' => CurrentTokenType::Comment,
+            _ => {
+                handle_defaults(c, &mut self.tokens, pos);
+                return;
+            }
+        };
+
+        if c != '"' {
+            self.concat_string.push(c);
+        }
+        self.current_type = Some(current_type);
+        self.current_type_start = pos;
+    }
+
+    /// Turn the pending literal into a token. `at_end` marks that the input
+    /// ended, which makes an incomplete literal an unterminated one instead of
+    /// an invalid one. In tolerant mode, broken literals become tokens too.
+    fn flush(&mut self, end: Position, at_end: bool) {
+        if self.current_type.is_none() {
+            return;
+        }
+
+        let range = Range {
+            start: self.current_type_start,
+            end,
+        };
+        let token = match self.literal_token(range, at_end) {
+            Ok(token) => token,
+            Err(error) if !self.tolerant => {
+                self.error = Some(error);
+                return;
+            }
+            Err(_) => self.recovered_token(range),
+        };
+
+        self.tokens.push(token);
+        self.reset();
+    }
+
+    fn literal_token(&self, range: Range, at_end: bool) -> ParseResult<TokenType> {
+        let literal = self.concat_string.as_str();
+
+        let token = match self.current_type {
+            None => return Err(ParseError::Error),
+            Some(CurrentTokenType::String) => return Err(ParseError::UnterminatedLiteral(range)),
+            Some(CurrentTokenType::Comment) => {
+                if literal == "/" {
+                    TokenType::Invalid(range, literal.to_owned())
+                } else {
+                    TokenType::Comment(range)
+                }
+            }
+            Some(CurrentTokenType::Number) => {
+                if !is_json_number(literal) {
+                    if at_end {
+                        return Err(ParseError::UnterminatedLiteral(range));
                     }
-                    tokens.push(TokenType::Comment(
+                    return Err(ParseError::InvalidType);
+                }
+                if let Ok(int) = literal.parse::<i64>() {
+                    TokenType::Int(range, int)
+                } else if let Ok(float) = literal.parse::<f64>() {
+                    TokenType::Float(range, float)
+                } else if at_end {
+                    return Err(ParseError::UnterminatedLiteral(range));
+                } else {
+                    return Err(ParseError::InvalidType);
+                }
+            }
+            Some(CurrentTokenType::Bool) => match literal {
+                "true" => TokenType::Bool(range, true),
+                "false" => TokenType::Bool(range, false),
+                _ if at_end && ("true".starts_with(literal) || "false".starts_with(literal)) => {
+                    return Err(ParseError::UnterminatedLiteral(range))
+                }
+                _ => return Err(ParseError::InvalidType),
+            },
+            Some(CurrentTokenType::Null) => match literal {
+                "null" => TokenType::Null(range),
+                _ if at_end && "null".starts_with(literal) => {
+                    return Err(ParseError::UnterminatedLiteral(range))
+                }
+                _ => return Err(ParseError::InvalidType),
+            },
+        };
+
+        Ok(token)
+    }
+
+    /// The token for a broken literal in tolerant mode. With `close_strings`,
+    /// an unterminated string is kept as a string. Its range is its content up
+    /// to the end of the line, there is no closing quote behind it.
+    fn recovered_token(&self, range: Range) -> TokenType {
+        match self.current_type {
+            Some(CurrentTokenType::String) if self.close_strings => TokenType::String(
+                Range {
+                    start: range.start + Position::new(0, 1, 1),
+                    end: range.end,
+                },
+                unescape(&self.concat_string),
+            ),
+            _ => TokenType::Invalid(range, self.concat_string.clone()),
+        }
+    }
+
+    fn next(&mut self, c: char, pos: Position) {
+        match self.current_type {
+            Some(CurrentTokenType::String) => {
+                if c == '\n' {
+                    return self.flush(pos, false);
+                }
+                if !self.escaped && c == '\\' {
+                    self.escaped = true;
+                    self.concat_string.push(c);
+                } else if !self.escaped && c == '"' {
+                    self.tokens.push(TokenType::String(
                         Range {
-                            start: current_type_start,
-                            end: Position::new(current_line, current_char, pos),
+                            start: self.current_type_start + Position::new(0, 1, 1),
+                            end: pos,
                         },
-                        concat_string.to_owned(),
+                        unescape(&self.concat_string),
                     ));
-
-                    return Ok(());
+                    self.reset();
+                } else {
+                    self.escaped = false;
+                    self.concat_string.push(c);
                 }
+                return;
             }
-            match &current_type {
-                Some(t) => match t {
-                    CurrentTokenType::String => {
-                        if !escaped && c == '\\' {
-                            escaped = true;
-                            concat_string.push(c);
-                        } else if !escaped && c == '"' {
-                            tokens.push(TokenType::String(
-                                Range {
-                                    start: current_type_start + Position::new(0, 1, 1),
-                                    end: Position::new(current_line, current_char, pos),
-                                },
-                                concat_string.to_owned(),
-                            ));
-                            current_type = None;
-                            concat_string = String::new();
-                        } else {
-                            escaped = false;
-                            concat_string.push(c);
-                        }
-                        ParseResult::Ok(())
-                    }
-                    CurrentTokenType::Number => {
-                        if !c.is_digit(10) && c != '.' {
-                            if let Ok(int) = concat_string.parse::<i64>() {
-                                tokens.push(TokenType::Int(
-                                    Range {
-                                        start: current_type_start,
-                                        end: Position::new(current_line, current_char, pos),
-                                    },
-                                    int,
-                                ));
-                            } else if let Ok(float) = concat_string.parse::<f64>() {
-                                tokens.push(TokenType::Float(
-                                    Range {
-                                        start: current_type_start,
-                                        end: Position::new(current_line, current_char, pos),
-                                    },
-                                    float,
-                                ));
-                            } else {
-                                return Err(ParseError::InvalidType);
-                            }
-                            current_type = None;
-                            concat_string = String::new();
-                            handle_defaults(
-                                c,
-                                &mut tokens,
-                                Position::new(current_line, current_char, pos),
-                            );
-                        } else {
-                            concat_string.push(c);
-                        }
-
-                        Ok(())
-                    }
-                    CurrentTokenType::Bool => {
-                        if concat_string == "true" || concat_string == "false" {
-                            tokens.push(TokenType::Bool(
-                                Range {
-                                    start: current_type_start,
-                                    end: Position::new(current_line, current_char, pos),
-                                },
-                                concat_string.parse::<bool>().unwrap(),
-                            ));
-                            current_type = None;
-                            concat_string = String::new();
-                            handle_defaults(
-                                c,
-                                &mut tokens,
-                                Position::new(current_line, current_char, pos),
-                            );
-                        } else if !"true".starts_with(&concat_string)
-                            && !"false".starts_with(&concat_string)
-                        {
-                            return Err(ParseError::InvalidType);
-                        } else {
-                            concat_string.push(c);
-                        }
-
-                        Ok(())
-                    }
-                    CurrentTokenType::Null => {
-                        if concat_string == "null" {
-                            tokens.push(TokenType::Null(Range {
-                                start: current_type_start,
-                                end: Position::new(current_line, current_char, pos),
-                            }));
-                            current_type = None;
-                            concat_string = String::new();
-                            handle_defaults(
-                                c,
-                                &mut tokens,
-                                Position::new(current_line, current_char, pos),
-                            );
-                        } else if !"null".starts_with(&concat_string) {
-                            return Err(ParseError::InvalidType);
-                        } else {
-                            concat_string.push(c);
-                        }
-
-                        Ok(())
-                    }
-                    CurrentTokenType::Comment => {
-                        if concat_string == "/" && c != '/' {
-                            current_type = None;
-                            concat_string = String::new();
-                        } else {
-                            concat_string.push(c);
-                        }
-
-                        Ok(())
-                    }
-                },
-                None => match c {
-                    '"' => {
-                        current_type = Some(CurrentTokenType::String);
-                        current_type_start = Position::new(current_line, current_char, pos);
-                        Ok(())
-                    }
-                    'n' => {
-                        current_type = Some(CurrentTokenType::Null);
-                        current_type_start = Position::new(current_line, current_char, pos);
-                        concat_string.push(c);
-                        Ok(())
-                    }
-                    't' | 'f' => {
-                        current_type = Some(CurrentTokenType::Bool);
-                        current_type_start = Position::new(current_line, current_char, pos);
-                        concat_string.push(c);
-                        Ok(())
-                    }
-                    '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                        current_type = Some(CurrentTokenType::Number);
-                        current_type_start = Position::new(current_line, current_char, pos);
-                        concat_string.push(c);
-                        Ok(())
-                    }
-                    '/' => {
-                        current_type = Some(CurrentTokenType::Comment);
-                        current_type_start = Position::new(current_line, current_char, pos);
-                        concat_string.push(c);
-                        Ok(())
-                    }
-                    _ => {
-                        handle_defaults(
-                            c,
-                            &mut tokens,
-                            Position::new(current_line, current_char, pos),
-                        );
-                        Ok(())
-                    }
-                },
+            Some(CurrentTokenType::Comment) => {
+                if self.concat_string == "/" && c != '/' {
+                    self.flush(pos, false);
+                } else if c == '\n' {
+                    return self.flush(pos, false);
+                } else {
+                    self.concat_string.push(c);
+                    return;
+                }
+            }
+            Some(CurrentTokenType::Number) => {
+                if is_number_char(c) {
+                    self.concat_string.push(c);
+                    return;
+                }
+                self.flush(pos, false);
+            }
+            Some(CurrentTokenType::Bool) | Some(CurrentTokenType::Null) => {
+                if c.is_ascii_alphabetic() {
+                    self.concat_string.push(c);
+                    return;
+                }
+                self.flush(pos, false);
             }
-            .ok()
-            .map(|_| current_char += 1)
-            .ok_or(ParseError::InvalidType)
-        })
-        .collect::<Result<Vec<()>, ParseError>>()
-        .map(|_| (tokens))
+            None => {}
+        }
+
+        self.start(c, pos);
+    }
+}
+
+pub fn tokenize(string: &str) -> ParseResult<Vec<TokenType>> {
+    let tokenizer = run(string, Tokenizer::default());
+    match tokenizer.error {
+        Some(error) => Err(error),
+        None => Ok(tokenizer.tokens),
+    }
+}
+
+/// Tokenize half-typed input. Instead of failing, broken literals become
+/// `TokenType::Invalid` tokens and unterminated strings are closed at the end
+/// of their line.
+pub fn tokenize_tolerant(string: &str) -> Vec<TokenType> {
+    let tokenizer = Tokenizer {
+        tolerant: true,
+        close_strings: true,
+        ..Tokenizer::default()
+    };
+    run(string, tokenizer).tokens
+}
+
+/// Tokenize any input. Broken literals, including unterminated strings, become
+/// `TokenType::Invalid` tokens covering their source.
+pub fn tokenize_with_errors(string: &str) -> Vec<TokenType> {
+    let tokenizer = Tokenizer {
+        tolerant: true,
+        ..Tokenizer::default()
+    };
+    run(string, tokenizer).tokens
+}
+
+/// Feed the text to the tokenizer. Unless it is tolerant, it stops at the
+/// first error.
+fn run(string: &str, mut tokenizer: Tokenizer) -> Tokenizer {
+    let mut current_line = 0;
+    let mut current_char = 0;
+
+    for (idx, c) in string.char_indices() {
+        tokenizer.next(c, Position::new(current_line, current_char, idx));
+        if tokenizer.error.is_some() {
+            return tokenizer;
+        }
+
+        if c == '\n' {
+            current_line += 1;
+            current_char = 0;
+        } else {
+            current_char += 1;
+        }
+    }
+
+    tokenizer.flush(
+        Position::new(current_line, current_char, string.len()),
+        true,
+    );
+    tokenizer
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{tokenize, Position, Range, TokenType};
+    use super::{
+        tokenize, tokenize_tolerant, tokenize_with_errors, unescape, ParseError, Position, Range,
+        TokenType,
+    };
     use float_cmp::approx_eq;
 
     #[test]
@@ -399,12 +567,12 @@ mod tests {
                     )),
                 );
 
-                equal_token_tuple(
+                equal_token_single(
                     tokens.get(29).unwrap(),
-                    &TokenType::Comment(
-                        Range::new(Position::new(1, 0, 103), Position::new(1, 6, 109)),
-                        "//bli ".to_owned(),
-                    ),
+                    &TokenType::Comment(Range::new(
+                        Position::new(1, 0, 103),
+                        Position::new(1, 6, 109),
+                    )),
                 );
 
                 equal_token_single(
@@ -415,26 +583,206 @@ mod tests {
                     )),
                 );
 
-                equal_token_tuple(
+                equal_token_single(
                     tokens.get(31).unwrap(),
-                    &TokenType::Comment(
-                        Range::new(Position::new(2, 2, 112), Position::new(2, 8, 118)),
-                        "// bla".to_owned(),
+                    &TokenType::Comment(Range::new(
+                        Position::new(2, 2, 112),
+                        Position::new(2, 8, 118),
+                    )),
+                );
+
+                equal_token_single(
+                    tokens.get(32).unwrap(),
+                    &TokenType::Comment(Range::new(
+                        Position::new(3, 1, 120),
+                        Position::new(3, 8, 127),
+                    )),
+                );
+            }
+            _ => panic!("Could not tokenize json."),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_escapes() {
+        match tokenize(r#"{ "a\"b": "tab\tnew\nline \u00e9 \ud83d\ude00 \\" }"#) {
+            Ok(tokens) => {
+                equal_token_tuple(
+                    tokens.get(1).unwrap(),
+                    &TokenType::String(
+                        Range::new(Position::new(0, 3, 3), Position::new(0, 7, 7)),
+                        "a\"b".to_owned(),
                     ),
                 );
+                equal_token_tuple(
+                    tokens.get(3).unwrap(),
+                    &TokenType::String(
+                        Range::new(Position::new(0, 11, 11), Position::new(0, 48, 48)),
+                        "tab\tnew\nline \u{e9} \u{1f600} \\".to_owned(),
+                    ),
+                );
+            }
+            _ => panic!("Could not tokenize json."),
+        }
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!("a\"b\\c/d", unescape(r#"a\"b\\c\/d"#));
+        assert_eq!("\u{8}\u{c}\n\r\t", unescape(r#"\b\f\n\r\t"#));
+        assert_eq!("é€", unescape(r#"\u00e9\u20AC"#));
+        assert_eq!("\u{1f600}", unescape(r#"\ud83d\ude00"#));
+        assert_eq!("x\u{1f600}y", unescape(r#"x\uD83D\uDE00y"#));
+    }
 
+    #[test]
+    fn test_unescape_invalid() {
+        // Unknown escapes and a trailing backslash stay as they are.
+        assert_eq!(r#"\x\q"#, unescape(r#"\x\q"#));
+        assert_eq!("a\\", unescape("a\\"));
+        // Malformed hex digits are kept.
+        assert_eq!(r#"\u12g4"#, unescape(r#"\u12g4"#));
+        assert_eq!(r#"\u12"#, unescape(r#"\u12"#));
+        assert_eq!(r#"\u+123"#, unescape(r#"\u+123"#));
+        // Lone surrogates are kept, a following escape is still decoded.
+        assert_eq!(r#"\ud83d"#, unescape(r#"\ud83d"#));
+        assert_eq!(r#"\ude00"#, unescape(r#"\ude00"#));
+        assert_eq!(r#"\ud83de"#, unescape(r#"\ud83d\u0065"#));
+    }
+
+    #[test]
+    fn test_tokenize_end_of_input() {
+        match tokenize("{ \"a\": 12") {
+            Ok(tokens) => equal_token_tuple(
+                tokens.last().unwrap(),
+                &TokenType::Int(
+                    Range::new(Position::new(0, 7, 7), Position::new(0, 9, 9)),
+                    12,
+                ),
+            ),
+            _ => panic!("Could not tokenize json."),
+        }
+
+        match tokenize("{ \"a\": tru") {
+            Err(ParseError::UnterminatedLiteral(range)) => equal_range(
+                &range,
+                &Range::new(Position::new(0, 7, 7), Position::new(0, 10, 10)),
+            ),
+            res => panic!("Should be unterminated literal, got '{:?}'", res),
+        }
+
+        match tokenize("{ \"a\": nul") {
+            Err(ParseError::UnterminatedLiteral(_)) => {}
+            res => panic!("Should be unterminated literal, got '{:?}'", res),
+        }
+
+        match tokenize("{ \"a\": \"b") {
+            Err(ParseError::UnterminatedLiteral(_)) => {}
+            res => panic!("Should be unterminated literal, got '{:?}'", res),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_numbers() {
+        for literal in &["0", "-0", "12", "-1.5", "0.25", "1e5", "1E+5", "2.5e-3"] {
+            let text = format!("{{ \"a\": {} }}", literal);
+            assert!(tokenize(&text).is_ok(), "'{}' should be a number", literal);
+        }
+
+        for literal in &["01", "-01", "1.", "-", "1.e5", "1e", "1e+", "1-2"] {
+            let text = format!("{{ \"a\": {} }}", literal);
+            match tokenize(&text) {
+                Err(ParseError::InvalidType) => {}
+                res => panic!("'{}' should be invalid, got '{:?}'", literal, res),
+            }
+        }
+
+        // A leading `.` or `+` does not start a number.
+        for literal in &[".5", "+1"] {
+            let text = format!("{{ \"a\": {} }}", literal);
+            let tokens = tokenize(&text).unwrap();
+            assert!(
+                matches!(tokens[3], TokenType::Invalid(..)),
+                "'{}' should start with an invalid token",
+                literal
+            );
+        }
+
+        let tokens = tokenize_with_errors("[01, 2]");
+        equal_token_tuple(
+            &tokens[1],
+            &TokenType::Invalid(
+                Range::new(Position::new(0, 1, 1), Position::new(0, 3, 3)),
+                "01".to_owned(),
+            ),
+        );
+    }
+
+    #[test]
+    fn test_tokenize_byte_offsets() {
+        // `char` counts characters, `idx` counts bytes of the utf-8 text.
+        match tokenize("{ \"é😀\": 1 }") {
+            Ok(tokens) => {
                 equal_token_tuple(
-                    tokens.get(32).unwrap(),
-                    &TokenType::Comment(
-                        Range::new(Position::new(3, 1, 120), Position::new(3, 8, 127)),
-                        "// blub".to_owned(),
+                    &tokens[1],
+                    &TokenType::String(
+                        Range::new(Position::new(0, 3, 3), Position::new(0, 5, 9)),
+                        "é😀".to_owned(),
+                    ),
+                );
+                equal_token_tuple(
+                    &tokens[3],
+                    &TokenType::Int(
+                        Range::new(Position::new(0, 8, 12), Position::new(0, 9, 13)),
+                        1,
                     ),
                 );
+                equal_token_single(
+                    &tokens[4],
+                    &TokenType::ObjectClose(Range::new(
+                        Position::new(0, 10, 14),
+                        Position::new(0, 11, 15),
+                    )),
+                );
             }
             _ => panic!("Could not tokenize json."),
         }
     }
 
+    #[test]
+    fn test_tokenize_tolerant() {
+        let tokens = tokenize_tolerant("{ \"a\": tru 1x,\n \"bé");
+        assert_eq!(8, tokens.len());
+        equal_token_tuple(
+            &tokens[3],
+            &TokenType::Invalid(
+                Range::new(Position::new(0, 7, 7), Position::new(0, 10, 10)),
+                "tru".to_owned(),
+            ),
+        );
+        // The unterminated string covers its content up to the end of the
+        // input.
+        equal_token_tuple(
+            &tokens[7],
+            &TokenType::String(
+                Range::new(Position::new(1, 2, 17), Position::new(1, 4, 20)),
+                "bé".to_owned(),
+            ),
+        );
+
+        // It ends at the end of its line, an empty one is empty.
+        let tokens = tokenize_tolerant("{ \"mode\": \"sl\n}");
+        equal_range(
+            &tokens[3].range(),
+            &Range::new(Position::new(0, 11, 11), Position::new(0, 13, 13)),
+        );
+        let tokens = tokenize_tolerant("{ \"mode\": \"\n}");
+        equal_range(
+            &tokens[3].range(),
+            &Range::new(Position::new(0, 11, 11), Position::new(0, 11, 11)),
+        );
+    }
+
     fn equal_token_tuple(token1: &TokenType, token2: &TokenType) {
         match (token1, token2) {
             (TokenType::String(r1, v1), TokenType::String(r2, v2)) => {
@@ -458,9 +806,9 @@ mod tests {
                 equal_range(r1, r2);
                 assert_eq!(v1, v2, "Int not equal. ('{}' != '{}')", v1, v2);
             }
-            (TokenType::Comment(r1, v1), TokenType::Comment(r2, v2)) => {
+            (TokenType::Invalid(r1, v1), TokenType::Invalid(r2, v2)) => {
                 equal_range(r1, r2);
-                assert_eq!(v1, v2, "Comment not equal. ('{}' != '{}')", v1, v2);
+                assert_eq!(v1, v2, "Invalid not equal. ('{}' != '{}')", v1, v2);
             }
             _ => panic!("Token token '{:?}' does not match '{:?}'", token1, token2),
         }
@@ -473,8 +821,9 @@ mod tests {
             | (TokenType::ArrayOpen(r1), TokenType::ArrayOpen(r2))
             | (TokenType::ArrayClose(r1), TokenType::ArrayClose(r2))
             | (TokenType::Null(r1), TokenType::Null(r2))
-            | (TokenType::Semicolon(r1), TokenType::Semicolon(r2))
-            | (TokenType::Comma(r1), TokenType::Comma(r2)) => equal_range(r1, r2),
+            | (TokenType::Colon(r1), TokenType::Colon(r2))
+            | (TokenType::Comma(r1), TokenType::Comma(r2))
+            | (TokenType::Comment(r1), TokenType::Comment(r2)) => equal_range(r1, r2),
             _ => panic!(
                 "Token token type '{:?}' does not match '{:?}'",
                 token1, token2
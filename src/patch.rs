@@ -0,0 +1,507 @@
+use super::edit::{self, indentation_at, position_at};
+use super::tree::{entries_equal, Entry, EntryType, Tree};
+use super::types::{ParseError, Range, TextEdit};
+use super::{parse_json, parse_json_value};
+use std::{error, fmt};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OpKind {
+    Add,
+    Remove,
+    Replace,
+    Move,
+    Copy,
+    Test,
+}
+
+/// An operation of a json patch (RFC 6902). Ranges are in the patch document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchOp {
+    pub kind: OpKind,
+    pub path: String,
+    pub from: Option<String>,
+    /// The value as written in the patch document.
+    pub value: Option<String>,
+    /// The range of the whole operation object.
+    pub range: Range,
+    pub path_range: Range,
+    pub from_range: Option<Range>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PatchError {
+    /// The patch document or the patched text is not valid json.
+    Parse(ParseError),
+    /// An operation is invalid or could not be applied. `op` is its index and
+    /// `range` the location of the problem in the patch document.
+    Op {
+        op: usize,
+        range: Range,
+        message: String,
+    },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchError::Parse(e) => write!(f, "{}", e),
+            PatchError::Op { op, range, message } => {
+                write!(f, "Operation {} at {}: {}", op, range.start, message)
+            }
+        }
+    }
+}
+
+impl error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PatchError::Parse(e) => Some(e),
+            PatchError::Op { .. } => None,
+        }
+    }
+}
+
+fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+fn array_index(token: &str) -> Option<usize> {
+    let valid = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    valid.then(|| token.parse().ok()).flatten()
+}
+
+/// Collects edits made one after another as edits of the original text.
+struct Composer<'a> {
+    original: &'a str,
+    current: String,
+    /// Byte ranges of the original text and their replacements, in order.
+    edits: Vec<(usize, usize, String)>,
+}
+
+impl<'a> Composer<'a> {
+    fn new(original: &'a str) -> Composer<'a> {
+        Composer {
+            original,
+            current: original.to_owned(),
+            edits: vec![],
+        }
+    }
+
+    /// Replace the bytes `start..end` of the current text.
+    fn replace(&mut self, start: usize, end: usize, new_text: &str) {
+        // Where the replacement of each edit is in the current text.
+        let mut spans = vec![];
+        let (mut current_end, mut original_end) = (0, 0);
+        self.edits.iter().for_each(|(edit_start, edit_end, text)| {
+            let span_start = current_end + (edit_start - original_end);
+            spans.push((span_start, span_start + text.len()));
+            current_end = span_start + text.len();
+            original_end = *edit_end;
+        });
+
+        let touching: Vec<usize> = (0..spans.len())
+            .filter(|idx| spans[*idx].0 <= end && start <= spans[*idx].1)
+            .collect();
+        match (touching.first(), touching.last()) {
+            (Some(&first), Some(&last)) => {
+                let (span_start, span_end) = (spans[first].0, spans[last].1);
+                let original_start = self.edits[first].0 - span_start.saturating_sub(start);
+                let original_end = self.edits[last].1 + end.saturating_sub(span_end);
+                let text = format!(
+                    "{}{}{}",
+                    &self.current[start.min(span_start)..start],
+                    new_text,
+                    &self.current[end..end.max(span_end)]
+                );
+                self.edits
+                    .splice(first..=last, [(original_start, original_end, text)]);
+            }
+            _ => {
+                let pos = spans
+                    .iter()
+                    .position(|(span_start, _)| *span_start > end)
+                    .unwrap_or(spans.len());
+                let (current_end, original_end) = match pos {
+                    0 => (0, 0),
+                    _ => (spans[pos - 1].1, self.edits[pos - 1].1),
+                };
+                self.edits.insert(
+                    pos,
+                    (
+                        original_end + (start - current_end),
+                        original_end + (end - current_end),
+                        new_text.to_owned(),
+                    ),
+                );
+            }
+        }
+        self.current.replace_range(start..end, new_text);
+    }
+
+    /// Apply edits of the current text.
+    fn apply(&mut self, mut edits: Vec<TextEdit>) {
+        // Later edits first, so the earlier ones stay valid.
+        edits.sort_by_key(|edit| edit.range.start.idx);
+        edits.reverse();
+        edits.iter().for_each(|edit| {
+            self.replace(edit.range.start.idx, edit.range.end.idx, &edit.new_text)
+        });
+    }
+
+    fn text_edits(&self) -> Vec<TextEdit> {
+        self.edits
+            .iter()
+            .map(|(start, end, text)| {
+                let range = Range::new(
+                    position_at(self.original, *start),
+                    position_at(self.original, *end),
+                );
+                TextEdit::new(range, text)
+            })
+            .collect()
+    }
+}
+
+/// The text of a value without the indentation of the line it starts on.
+fn value_text(text: &str, entry: &Entry) -> String {
+    let range = entry.source_range();
+    let indent = indentation_at(text, range.start.idx);
+    text[range.start.idx..range.end.idx]
+        .split('\n')
+        .map(|line| line.strip_prefix(indent).unwrap_or(line))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// A json patch document (RFC 6902).
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::{patch::JsonPatch, types::TextEdit};
+/// let text = "{\n  // the port\n  \"port\": 8080\n}";
+/// let patch = JsonPatch::parse(r#"[{ "op": "replace", "path": "/port", "value": 9090 }]"#).unwrap();
+/// let edits = patch.edits(text).unwrap();
+/// assert_eq!("{\n  // the port\n  \"port\": 9090\n}", TextEdit::apply(text, &edits));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPatch {
+    pub ops: Vec<PatchOp>,
+}
+
+impl JsonPatch {
+    pub fn parse(text: &str) -> Result<JsonPatch, PatchError> {
+        let tree = parse_json_value(text).map_err(PatchError::Parse)?;
+        let ops = tree
+            .entry_at_pointer("/")
+            .filter(|entry| matches!(entry.entry_type, EntryType::JSONArray(_)))
+            .ok_or(PatchError::Parse(ParseError::InvalidType))?;
+
+        tree.array_entries(ops)
+            .iter()
+            .enumerate()
+            .map(|(idx, op)| Self::parse_op(&tree, text, idx, op))
+            .collect::<Result<Vec<PatchOp>, PatchError>>()
+            .map(|ops| JsonPatch { ops })
+    }
+
+    fn parse_op(tree: &Tree, text: &str, idx: usize, op: &Entry) -> Result<PatchOp, PatchError> {
+        let range = op.range;
+        let error = |range: Range, message: String| PatchError::Op {
+            op: idx,
+            range,
+            message,
+        };
+        if !matches!(op.entry_type, EntryType::JSONObject(_)) {
+            return Err(error(range, "Operation is not an object".to_owned()));
+        }
+
+        let members = tree.object_entries(op);
+        let member = |name: &str| members.iter().find(|(key, _)| key.name == name);
+        let string = |name: &str| match member(name) {
+            Some((_, value)) => match &value.entry_type {
+                EntryType::String(s) => Ok(Some((s.clone(), value.source_range()))),
+                _ => Err(error(
+                    value.source_range(),
+                    format!("\"{}\" is not a string", name),
+                )),
+            },
+            None => Ok(None),
+        };
+        let required =
+            |name: &str| string(name)?.ok_or_else(|| error(range, format!("Missing \"{}\"", name)));
+
+        let (name, name_range) = required("op")?;
+        let kind = match name.as_str() {
+            "add" => OpKind::Add,
+            "remove" => OpKind::Remove,
+            "replace" => OpKind::Replace,
+            "move" => OpKind::Move,
+            "copy" => OpKind::Copy,
+            "test" => OpKind::Test,
+            _ => return Err(error(name_range, format!("Unknown operation \"{}\"", name))),
+        };
+        let (path, path_range) = required("path")?;
+        let from = match kind {
+            OpKind::Move | OpKind::Copy => Some(required("from")?),
+            _ => None,
+        };
+        let value = match kind {
+            OpKind::Add | OpKind::Replace | OpKind::Test => {
+                let (_, value) =
+                    member("value").ok_or_else(|| error(range, "Missing \"value\"".to_owned()))?;
+                Some(value_text(text, value))
+            }
+            _ => None,
+        };
+
+        Ok(PatchOp {
+            kind,
+            path,
+            from: from.as_ref().map(|(from, _)| from.clone()),
+            value,
+            range,
+            path_range,
+            from_range: from.map(|(_, range)| range),
+        })
+    }
+
+    /// Get the edits of `text` applying the operations one after another.
+    /// Nothing outside of the changed values is touched, so comments and
+    /// formatting are kept.
+    pub fn edits(&self, text: &str) -> Result<Vec<TextEdit>, PatchError> {
+        parse_json(text).map_err(PatchError::Parse)?;
+
+        let mut composer = Composer::new(text);
+        self.ops.iter().enumerate().try_for_each(|(idx, op)| {
+            apply_op(&mut composer, op).map_err(|(range, message)| PatchError::Op {
+                op: idx,
+                range,
+                message,
+            })
+        })?;
+        Ok(composer.text_edits())
+    }
+}
+
+type OpResult<T> = Result<T, (Range, String)>;
+
+fn current_tree(composer: &Composer) -> OpResult<Tree> {
+    parse_json(&composer.current)
+        .map_err(|e| (Range::default(), format!("Patched text is invalid: {}", e)))
+}
+
+/// Find the value at `pointer` in the current text.
+fn find<'t>(tree: &'t Tree, pointer: &str, range: Range) -> OpResult<&'t Entry> {
+    tree.entry_at_pointer(pointer)
+        .ok_or_else(|| (range, format!("Path not found: {}", pointer)))
+}
+
+fn add(composer: &mut Composer, pointer: &str, range: Range, value: &str) -> OpResult<()> {
+    let tree = current_tree(composer)?;
+    let text = &composer.current;
+    let invalid = |e: ParseError| (range, e.to_string());
+    let (parent_pointer, last) = match pointer.rfind('/') {
+        Some(idx) => (&pointer[..idx], unescape(&pointer[idx + 1..])),
+        None if pointer.is_empty() => {
+            let edits = tree.set_value_edits(text, &[], value).map_err(invalid)?;
+            composer.apply(edits);
+            return Ok(());
+        }
+        None => return Err((range, format!("Invalid pointer: {}", pointer))),
+    };
+    let parent = find(&tree, parent_pointer, range)?;
+    let path = tree.path_of(parent);
+    let path = path.as_path_types();
+    let edits = match &parent.entry_type {
+        EntryType::JSONObject(_) => tree.insert_key_edits(text, &path, &last, value),
+        EntryType::JSONArray(array) => {
+            let idx = match last.as_str() {
+                "-" => Some(array.len()),
+                token => array_index(token).filter(|idx| *idx <= array.len()),
+            }
+            .ok_or_else(|| (range, format!("Invalid array index: {}", last)))?;
+            tree.insert_array_value_edits(text, &path, idx, value)
+        }
+        _ => return Err((range, format!("Path not found: {}", pointer))),
+    }
+    .map_err(invalid)?;
+    composer.apply(edits);
+    Ok(())
+}
+
+fn remove(composer: &mut Composer, pointer: &str, range: Range) -> OpResult<()> {
+    let tree = current_tree(composer)?;
+    let entry = find(&tree, pointer, range)?;
+    if pointer.is_empty() {
+        return Err((range, "Cannot remove the root".to_owned()));
+    }
+
+    let path = tree.path_of(entry);
+    let edits = tree
+        .remove_edits(&composer.current, &path.as_path_types())
+        .map_err(|e| (range, e.to_string()))?;
+    composer.apply(edits);
+    Ok(())
+}
+
+fn apply_op(composer: &mut Composer, op: &PatchOp) -> OpResult<()> {
+    let value = op.value.as_deref().unwrap_or("null");
+    let from = op.from.as_deref().unwrap_or("");
+    let from_range = op.from_range.unwrap_or(op.range);
+
+    match op.kind {
+        OpKind::Add => add(composer, &op.path, op.path_range, value),
+        OpKind::Remove => remove(composer, &op.path, op.path_range),
+        OpKind::Replace => {
+            let tree = current_tree(composer)?;
+            let entry = find(&tree, &op.path, op.path_range)?;
+            let path = tree.path_of(entry);
+            let edits = tree
+                .set_value_edits(&composer.current, &path.as_path_types(), value)
+                .map_err(|e| (op.path_range, e.to_string()))?;
+            composer.apply(edits);
+            Ok(())
+        }
+        OpKind::Move | OpKind::Copy => {
+            let tree = current_tree(composer)?;
+            let value = value_text(&composer.current, find(&tree, from, from_range)?);
+            if op.kind == OpKind::Move {
+                if op.path.starts_with(&format!("{}/", from)) {
+                    return Err((op.path_range, "Cannot move a value into itself".to_owned()));
+                }
+                if op.path == from {
+                    return Ok(());
+                }
+                remove(composer, from, from_range)?;
+            }
+            add(composer, &op.path, op.path_range, &value)
+        }
+        OpKind::Test => {
+            let tree = current_tree(composer)?;
+            let entry = find(&tree, &op.path, op.path_range)?;
+            let expected = edit::parse_value(value).map_err(|e| (op.range, e.to_string()))?;
+            let equal = expected
+                .entry_at_pointer("/")
+                .is_some_and(|value| entries_equal(&tree, entry, &expected, value));
+            if !equal {
+                let message = format!("Test failed, {} is not equal to {}", op.path, value);
+                return Err((op.range, message));
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonPatch, OpKind, PatchError};
+    use crate::types::{ParseError, TextEdit};
+
+    const TEXT: &str = "{\n  // servers\n  \"servers\": [\n    { \"host\": \"a\" },\n    { \"host\": \"b\" }\n  ],\n  \"debug\": false // off\n}";
+
+    fn patched(patch: &str) -> String {
+        let edits = JsonPatch::parse(patch).unwrap().edits(TEXT).unwrap();
+        TextEdit::apply(TEXT, &edits)
+    }
+
+    #[test]
+    fn test_parse_patch() {
+        let patch = JsonPatch::parse(
+            "[\n  { \"op\": \"move\", \"from\": \"/a\", \"path\": \"/b\" },\n  { \"op\": \"add\", \"path\": \"/c\", \"value\": [1, 2] }\n]",
+        )
+        .unwrap();
+        assert_eq!(OpKind::Move, patch.ops[0].kind);
+        assert_eq!(Some("/a".to_owned()), patch.ops[0].from);
+        assert_eq!(2, patch.ops[1].range.start.line);
+        assert_eq!(2, patch.ops[1].range.start.char);
+        assert_eq!(25, patch.ops[1].path_range.start.char);
+        assert_eq!(Some("[1, 2]".to_owned()), patch.ops[1].value);
+
+        match JsonPatch::parse("[{ \"op\": \"add\", \"path\": \"/a\" }, { \"op\": \"take\" }]") {
+            Err(PatchError::Op { op, range, .. }) => {
+                assert_eq!(0, op);
+                assert_eq!(1, range.start.char);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(JsonPatch::parse("[{ \"op\": ").is_err());
+        assert!(matches!(
+            JsonPatch::parse("{}"),
+            Err(PatchError::Parse(ParseError::InvalidType))
+        ));
+        match JsonPatch::parse("[], \"x\": 1") {
+            Err(PatchError::Parse(ParseError::TrailingContent(range))) => {
+                assert_eq!((2, 10), (range.start.idx, range.end.idx));
+            }
+            other => panic!("{:?}", other),
+        }
+        match JsonPatch::parse("// ops\n[\n  { \"op\": 1 }\n]") {
+            Err(PatchError::Op { range, .. }) => {
+                assert_eq!((2, 10), (range.start.line, range.start.char));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_patch_edits() {
+        assert_eq!(
+            "{\n  // servers\n  \"servers\": [\n    { \"host\": \"a\", \"port\": 80 },\n    { \"host\": \"c\" },\n    { \"host\": \"b\" }\n  ],\n  \"debug\": true // off\n}",
+            patched(
+                r#"[
+                    { "op": "test", "path": "/servers/0/host", "value": "a" },
+                    { "op": "add", "path": "/servers/0/port", "value": 80 },
+                    { "op": "copy", "from": "/servers/1", "path": "/servers/-" },
+                    { "op": "replace", "path": "/servers/1/host", "value": "c" },
+                    { "op": "replace", "path": "/debug", "value": true }
+                ]"#
+            )
+        );
+        assert_eq!(
+            "{\n  // servers\n  \"servers\": [\n    { \"host\": \"b\" }\n  ],\n  \"debug\": false, // off\n  \"first\": { \"host\": \"a\" }\n}",
+            patched(r#"[{ "op": "move", "from": "/servers/0", "path": "/first" }]"#)
+        );
+    }
+
+    #[test]
+    fn test_patch_errors() {
+        let patch = JsonPatch::parse(
+            "[\n  { \"op\": \"remove\", \"path\": \"/debug\" },\n  { \"op\": \"test\", \"path\": \"/debug\", \"value\": false }\n]",
+        )
+        .unwrap();
+        match patch.edits(TEXT) {
+            Err(PatchError::Op { op, range, message }) => {
+                assert_eq!(1, op);
+                assert_eq!(2, range.start.line);
+                assert_eq!(26, range.start.char);
+                assert_eq!("Path not found: /debug", message);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let patch =
+            JsonPatch::parse(r#"[{ "op": "test", "path": "/debug", "value": true }]"#).unwrap();
+        assert!(matches!(
+            patch.edits(TEXT),
+            Err(PatchError::Op { op: 0, .. })
+        ));
+
+        let patch =
+            JsonPatch::parse(r#"[{ "op": "add", "path": "/servers/5", "value": 1 }]"#).unwrap();
+        assert!(matches!(
+            patch.edits(TEXT),
+            Err(PatchError::Op { op: 0, .. })
+        ));
+
+        // The value of a test is a single value, members can't be added to it.
+        let mut patch =
+            JsonPatch::parse(r#"[{ "op": "test", "path": "/debug", "value": false }]"#).unwrap();
+        patch.ops[0].value = Some("false, \"x\": 1".to_owned());
+        assert!(matches!(
+            patch.edits(TEXT),
+            Err(PatchError::Op { op: 0, .. })
+        ));
+    }
+}
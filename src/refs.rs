@@ -0,0 +1,465 @@
+use super::parse_json;
+use super::tree::{Entry, EntryType, Tree};
+use super::types::{ParseError, Range};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::{error, fmt, fs, io};
+
+/// Loads the text of the files references point to, e.g. from disk or from
+/// the buffers of an editor. Files are named like in the references, with
+/// relative references joined to the name of the referring file.
+pub trait Loader {
+    fn load(&mut self, file: &str) -> io::Result<String>;
+}
+
+impl<F: FnMut(&str) -> io::Result<String>> Loader for F {
+    fn load(&mut self, file: &str) -> io::Result<String> {
+        self(file)
+    }
+}
+
+/// Loads files from disk, relative to a directory.
+pub struct FileLoader {
+    root: PathBuf,
+}
+
+impl FileLoader {
+    pub fn new<P: Into<PathBuf>>(root: P) -> FileLoader {
+        FileLoader { root: root.into() }
+    }
+}
+
+impl Loader for FileLoader {
+    fn load(&mut self, file: &str) -> io::Result<String> {
+        fs::read_to_string(self.root.join(file))
+    }
+}
+
+/// A `$ref` in a loaded file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub file: String,
+    /// The reference, e.g. `other.json#/definitions/port`.
+    pub reference: String,
+    /// The range of the reference string, including its quotes.
+    pub range: Range,
+}
+
+#[derive(Debug)]
+pub enum RefError {
+    /// A file could not be loaded.
+    Io(String, io::Error),
+    /// A file is not valid json.
+    Parse(String, ParseError),
+    /// The entry is neither an object with a `$ref` nor the `$ref` string.
+    NotARef,
+    /// The target of the reference does not exist, or its file could not be
+    /// loaded.
+    NotFound(Reference),
+    /// The references of a chain leading back to its start.
+    Cycle(Vec<Reference>),
+}
+
+impl fmt::Display for RefError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RefError::Io(file, e) => write!(f, "Could not load {}: {}", file, e),
+            RefError::Parse(file, e) => write!(f, "{}: {}", file, e),
+            RefError::NotARef => write!(f, "Not a $ref"),
+            RefError::NotFound(r) => write!(
+                f,
+                "{}:{}: Reference {} not found",
+                r.file, r.range.start, r.reference
+            ),
+            RefError::Cycle(chain) => {
+                let references: Vec<&str> = chain.iter().map(|r| r.reference.as_str()).collect();
+                write!(f, "Reference cycle {}", references.join(" -> "))
+            }
+        }
+    }
+}
+
+impl error::Error for RefError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RefError::Io(_, e) => Some(e),
+            RefError::Parse(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A loaded file.
+pub struct Document {
+    pub text: String,
+    pub tree: Tree,
+}
+
+/// The target of a reference.
+#[derive(Debug, Copy, Clone)]
+pub struct Target<'a> {
+    pub file: &'a str,
+    pub entry: &'a Entry,
+    /// The source range of the entry.
+    pub range: Range,
+}
+
+/// Join a relative file reference to the name of the referring file, e.g.
+/// `../b.json` and `defs/a.json` to `b.json`.
+fn join(file: &str, other: &str) -> String {
+    let mut parts: Vec<&str> = match other.strip_prefix('/') {
+        Some(_) => vec![""],
+        None => file.split('/').collect(),
+    };
+    if !other.starts_with('/') {
+        parts.pop();
+    }
+
+    for part in other.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => match parts.last() {
+                None | Some(&"..") => parts.push(".."),
+                Some(&"") => {}
+                Some(_) => {
+                    parts.pop();
+                }
+            },
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Split a reference into the file it points to and the json pointer.
+fn split<'r>(file: &str, reference: &'r str) -> (String, &'r str) {
+    let (path, pointer) = match reference.find('#') {
+        Some(idx) => (&reference[..idx], &reference[idx + 1..]),
+        None => (reference, ""),
+    };
+    if path.is_empty() {
+        (file.to_owned(), pointer)
+    } else {
+        (join(file, path), pointer)
+    }
+}
+
+/// Get the `$ref` string of an object entry, or the entry if it is the
+/// string of a `$ref` member.
+fn ref_string<'a>(tree: &'a Tree, entry: &'a Entry) -> Option<(&'a str, &'a Entry)> {
+    let value = match &entry.entry_type {
+        EntryType::JSONObject(hash) => hash
+            .get("$ref")
+            .and_then(|(_, value)| tree.entries.get(*value))?,
+        EntryType::String(_) => {
+            let key = entry.key.and_then(|key| tree.keys.get(key))?;
+            if key.name != "$ref" {
+                return None;
+            }
+            entry
+        }
+        _ => return None,
+    };
+    match &value.entry_type {
+        EntryType::String(reference) => Some((reference, value)),
+        _ => None,
+    }
+}
+
+fn references_of(file: &str, tree: &Tree) -> Vec<Reference> {
+    let mut references: Vec<Reference> = tree
+        .entries
+        .iter()
+        .filter(|entry| matches!(entry.entry_type, EntryType::JSONObject(_)))
+        .filter_map(|entry| ref_string(tree, entry))
+        .map(|(reference, value)| Reference {
+            file: file.to_owned(),
+            reference: reference.to_owned(),
+            range: value.source_range(),
+        })
+        .collect();
+    references.sort_by_key(|r| r.range.start.idx);
+    references
+}
+
+/// Files loaded with the files their `$ref`s point to, for resolving
+/// references across files.
+pub struct Workspace<L: Loader> {
+    loader: L,
+    documents: HashMap<String, Document>,
+}
+
+impl<L: Loader> Workspace<L> {
+    pub fn new(loader: L) -> Workspace<L> {
+        Workspace {
+            loader,
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Load a file, and all files its references lead to. Files which are
+    /// already loaded are kept. Only errors of `file` itself are returned,
+    /// referenced files which can not be loaded are left out.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::refs::Workspace;
+    /// let mut workspace = Workspace::new(|file: &str| match file {
+    ///     "main.json" => Ok("{ \"port\": { \"$ref\": \"defs/port.json\" } }".to_owned()),
+    ///     _ => Ok("{ \"type\": \"integer\" }".to_owned()),
+    /// });
+    /// workspace.load("main.json").unwrap();
+    /// assert!(workspace.document("defs/port.json").is_some());
+    /// ```
+    pub fn load(&mut self, file: &str) -> Result<&Document, RefError> {
+        if !self.documents.contains_key(file) {
+            let document = self.load_document(file)?;
+            let mut queue = vec![(file.to_owned(), document)];
+            let mut attempted = HashSet::new();
+
+            while let Some((name, document)) = queue.pop() {
+                for reference in references_of(&name, &document.tree) {
+                    let (target, _) = split(&name, &reference.reference);
+                    let known = self.documents.contains_key(&target)
+                        || target == name
+                        || queue.iter().any(|(queued, _)| *queued == target);
+                    if known || !attempted.insert(target.clone()) {
+                        continue;
+                    }
+                    if let Ok(document) = self.load_document(&target) {
+                        queue.push((target, document));
+                    }
+                }
+                self.documents.insert(name, document);
+            }
+        }
+        Ok(&self.documents[file])
+    }
+
+    fn load_document(&mut self, file: &str) -> Result<Document, RefError> {
+        let text = self
+            .loader
+            .load(file)
+            .map_err(|e| RefError::Io(file.to_owned(), e))?;
+        let tree = parse_json(&text).map_err(|e| RefError::Parse(file.to_owned(), e))?;
+        Ok(Document { text, tree })
+    }
+
+    /// Get a loaded file.
+    pub fn document(&self, file: &str) -> Option<&Document> {
+        self.documents.get(file)
+    }
+
+    /// Get the files loaded so far.
+    pub fn files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self.documents.keys().map(|file| file.as_str()).collect();
+        files.sort_unstable();
+        files
+    }
+
+    /// Follow a reference, e.g. for go to definition. `entry` is the index of
+    /// an object with a `$ref` or of the `$ref` string in the tree of the
+    /// loaded `file`.
+    /// Targets which are references themselves are followed as well.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::refs::Workspace;
+    /// let mut workspace = Workspace::new(|file: &str| match file {
+    ///     "main.json" => Ok("{ \"port\": { \"$ref\": \"defs/port.json#/port\" } }".to_owned()),
+    ///     _ => Ok("{\n  \"port\": { \"type\": \"integer\" }\n}".to_owned()),
+    /// });
+    /// workspace.load("main.json").unwrap();
+    /// let tree = &workspace.document("main.json").unwrap().tree;
+    /// let port = tree.index_at_pointer("/port").unwrap();
+    ///
+    /// let target = workspace.resolve_ref("main.json", port).unwrap();
+    /// assert_eq!("defs/port.json", target.file);
+    /// assert_eq!(1, target.range.start.line);
+    /// ```
+    pub fn resolve_ref(&self, file: &str, entry: usize) -> Result<Target<'_>, RefError> {
+        let tree = &self.document(file).ok_or(RefError::NotARef)?.tree;
+        let object = match tree.entries.get(entry).ok_or(RefError::NotARef)? {
+            Entry {
+                entry_type: EntryType::String(_),
+                parent,
+                ..
+            } => parent.ok_or(RefError::NotARef)?,
+            _ => entry,
+        };
+        let mut current = (file, object);
+        let mut visited = HashSet::new();
+        let mut chain = vec![];
+
+        loop {
+            visited.insert(current);
+            let (file, idx) = current;
+            let tree = &self.documents[file].tree;
+            let (reference, value) =
+                ref_string(tree, &tree.entries[idx]).ok_or(RefError::NotARef)?;
+            let link = Reference {
+                file: file.to_owned(),
+                reference: reference.to_owned(),
+                range: value.source_range(),
+            };
+
+            let (target_file, pointer) = split(file, reference);
+            let target = self
+                .documents
+                .get_key_value(&target_file)
+                .and_then(|(name, document)| {
+                    let idx = document.tree.index_at_pointer(pointer)?;
+                    Some((name.as_str(), &document.tree, idx))
+                });
+            let (target_file, target_tree, target_idx) = match target {
+                Some(target) => target,
+                None => return Err(RefError::NotFound(link)),
+            };
+            chain.push(link);
+            let target = &target_tree.entries[target_idx];
+
+            let is_ref = matches!(target.entry_type, EntryType::JSONObject(_))
+                && ref_string(target_tree, target).is_some();
+            if !is_ref {
+                return Ok(Target {
+                    file: target_file,
+                    entry: target,
+                    range: target.source_range(),
+                });
+            }
+
+            let next = (target_file, target_idx);
+            if visited.contains(&next) {
+                return Err(RefError::Cycle(chain));
+            }
+            current = next;
+        }
+    }
+
+    /// Index the references of all loaded files by the entry they point to,
+    /// for finding all references to a definition.
+    pub fn reference_index(&self) -> ReferenceIndex {
+        let mut references: HashMap<(String, usize), Vec<Reference>> = HashMap::new();
+        for file in self.files() {
+            for reference in references_of(file, &self.documents[file].tree) {
+                let (target_file, pointer) = split(file, &reference.reference);
+                let target = self
+                    .documents
+                    .get(&target_file)
+                    .and_then(|document| document.tree.index_at_pointer(pointer));
+                if let Some(idx) = target {
+                    references
+                        .entry((target_file, idx))
+                        .or_default()
+                        .push(reference);
+                }
+            }
+        }
+        ReferenceIndex { references }
+    }
+}
+
+/// The references of loaded files, by the entry they point to.
+pub struct ReferenceIndex {
+    references: HashMap<(String, usize), Vec<Reference>>,
+}
+
+impl ReferenceIndex {
+    /// Get the references pointing directly to an entry of the tree of
+    /// `file`, given by its index, ordered by file and position.
+    pub fn references_to(&self, file: &str, entry: usize) -> &[Reference] {
+        self.references
+            .get(&(file.to_owned(), entry))
+            .map_or(&[], |references| references.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{join, RefError, Workspace};
+    use std::io;
+
+    fn loader(file: &str) -> io::Result<String> {
+        let text = match file {
+            "main.json" => "{\n  \"definitions\": {\n    \"a\": { \"$ref\": \"#/definitions/b\" },\n    \"b\": { \"$ref\": \"defs/common.json#/port\" },\n    \"loop\": { \"$ref\": \"defs/common.json#/loop\" },\n    \"missing\": { \"$ref\": \"none.json#/x\" }\n  },\n  \"server\": { \"$ref\": \"#/definitions/a\" }\n}",
+            "defs/common.json" => "{\n  \"port\": { \"type\": \"integer\" },\n  \"loop\": { \"$ref\": \"../main.json#/definitions/loop\" },\n  \"other\": { \"$ref\": \"#/port\" }\n}",
+            _ => return Err(io::Error::new(io::ErrorKind::NotFound, file)),
+        };
+        Ok(text.to_owned())
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!("b.json", join("a.json", "b.json"));
+        assert_eq!("defs/b.json", join("a.json", "./defs/b.json"));
+        assert_eq!("b.json", join("defs/a.json", "../b.json"));
+        assert_eq!("../b.json", join("a.json", "../b.json"));
+        assert_eq!("/b.json", join("defs/a.json", "/b.json"));
+    }
+
+    #[test]
+    fn test_resolve_ref() {
+        let mut workspace = Workspace::new(loader);
+        assert!(matches!(
+            workspace.load("none.json"),
+            Err(RefError::Io(_, _))
+        ));
+        workspace.load("main.json").unwrap();
+        assert_eq!(vec!["defs/common.json", "main.json"], workspace.files());
+
+        let tree = &workspace.document("main.json").unwrap().tree;
+        let server = tree.index_at_pointer("/server").unwrap();
+        let target = workspace.resolve_ref("main.json", server).unwrap();
+        assert_eq!("defs/common.json", target.file);
+        assert_eq!((1, 10), (target.range.start.line, target.range.start.char));
+
+        // The `$ref` string resolves like its object.
+        let string = tree.index_at_pointer("/server/$ref").unwrap();
+        let target = workspace.resolve_ref("main.json", string).unwrap();
+        assert_eq!((1, 10), (target.range.start.line, target.range.start.char));
+
+        let entry = tree.index_at_pointer("/definitions/loop").unwrap();
+        match workspace.resolve_ref("main.json", entry) {
+            Err(RefError::Cycle(chain)) => {
+                let files: Vec<&str> = chain.iter().map(|r| r.file.as_str()).collect();
+                assert_eq!(vec!["main.json", "defs/common.json"], files);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let entry = tree.index_at_pointer("/definitions/missing").unwrap();
+        match workspace.resolve_ref("main.json", entry) {
+            Err(RefError::NotFound(reference)) => {
+                assert_eq!("none.json#/x", reference.reference);
+                assert_eq!(5, reference.range.start.line);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let entry = tree.index_at_pointer("/definitions").unwrap();
+        assert!(matches!(
+            workspace.resolve_ref("main.json", entry),
+            Err(RefError::NotARef)
+        ));
+    }
+
+    #[test]
+    fn test_reference_index() {
+        let mut workspace = Workspace::new(loader);
+        workspace.load("main.json").unwrap();
+        let index = workspace.reference_index();
+
+        let common = &workspace.document("defs/common.json").unwrap().tree;
+        let port = common.index_at_pointer("/port").unwrap();
+        let references: Vec<(&str, usize)> = index
+            .references_to("defs/common.json", port)
+            .iter()
+            .map(|r| (r.file.as_str(), r.range.start.line))
+            .collect();
+        assert_eq!(vec![("defs/common.json", 3), ("main.json", 3)], references);
+
+        let main = &workspace.document("main.json").unwrap().tree;
+        let server = main.index_at_pointer("/server").unwrap();
+        assert!(index.references_to("main.json", server).is_empty());
+    }
+}
@@ -0,0 +1,709 @@
+use super::parse_json;
+use super::tree::{entries_equal, number, Entry, EntryType, JsonPath, PathSegment, Tree};
+use super::types::{Diagnostic, ParseResult, Range, Severity};
+use super::writer::{write_entry, WriteOptions};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Stops `$ref` loops which never reach a value.
+const MAX_DEPTH: usize = 64;
+
+/// Integers are checked exactly. Other numbers are checked with a tolerance
+/// relative to the quotient, as e.g. `0.3 / 0.1` is not exactly `3.0`.
+fn is_multiple_of(value: &Entry, factor: &Entry) -> bool {
+    match (&value.entry_type, &factor.entry_type) {
+        (EntryType::Int(value), EntryType::Int(factor)) => value % factor == 0,
+        _ => match (number(value), number(factor)) {
+            (Some(value), Some(factor)) => {
+                let quotient = value / factor;
+                (quotient - quotient.round()).abs() <= 1e-9 * quotient.abs().max(1.0)
+            }
+            _ => true,
+        },
+    }
+}
+
+fn type_name(entry: &Entry) -> &'static str {
+    match entry.entry_type {
+        EntryType::JSONObject(_) => "object",
+        EntryType::JSONArray(_) => "array",
+        EntryType::String(_) => "string",
+        EntryType::Int(_) => "integer",
+        EntryType::Float(_) => "number",
+        EntryType::Bool(_) => "boolean",
+        EntryType::Null => "null",
+    }
+}
+
+fn count(entry: &Entry) -> Option<usize> {
+    number(entry).filter(|n| *n >= 0.0).map(|n| n as usize)
+}
+
+fn matches_type(entry: &Entry, name: &str) -> bool {
+    match (name, &entry.entry_type) {
+        ("integer", EntryType::Float(value)) => value.fract() == 0.0,
+        ("number", EntryType::Int(_)) => true,
+        _ => type_name(entry) == name,
+    }
+}
+
+fn error(out: &mut Vec<Diagnostic>, range: Range, message: &str, code: &str) {
+    out.push(Diagnostic::new(range, Severity::Error, message, code));
+}
+
+/// A json schema, parsed with this crate. Supports the core keywords of draft
+/// 2020-12 and `$ref`s within the schema document.
+pub struct Schema {
+    pub tree: Tree,
+    /// The compiled `pattern`s and `patternProperties` of the schema, by their
+    /// source. Invalid patterns are left out.
+    patterns: HashMap<String, Regex>,
+}
+
+impl Schema {
+    pub fn new(tree: Tree) -> Schema {
+        let mut sources = vec![];
+        tree.entries.iter().for_each(|entry| {
+            if let EntryType::JSONObject(hash) = &entry.entry_type {
+                let member = |name| {
+                    hash.get(name)
+                        .and_then(|(_, value)| tree.entries.get(*value))
+                };
+                if let Some(EntryType::String(pattern)) = member("pattern").map(|e| &e.entry_type) {
+                    sources.push(pattern.as_str());
+                }
+                if let Some(properties) = member("patternProperties") {
+                    sources.extend(
+                        tree.object_entries(properties)
+                            .into_iter()
+                            .map(|(key, _)| key.name.as_str()),
+                    );
+                }
+            }
+        });
+
+        let patterns = sources
+            .into_iter()
+            .filter_map(|source| Some((source.to_owned(), Regex::new(source).ok()?)))
+            .collect();
+        Schema { tree, patterns }
+    }
+
+    /// Get the compiled regex of a pattern of the schema.
+    fn regex(&self, pattern: &str) -> Option<&Regex> {
+        self.patterns.get(pattern)
+    }
+
+    pub fn parse(text: &str) -> ParseResult<Schema> {
+        parse_json(text).map(Schema::new)
+    }
+
+    /// Get the value of a keyword of a schema object.
+    pub fn keyword(&self, schema: &Entry, name: &str) -> Option<&Entry> {
+        match &schema.entry_type {
+            EntryType::JSONObject(hash) => hash
+                .get(name)
+                .and_then(|(_, value)| self.tree.entries.get(*value)),
+            _ => None,
+        }
+    }
+
+    /// Get the value of a string keyword of a schema object.
+    pub fn keyword_str(&self, schema: &Entry, name: &str) -> Option<&str> {
+        match self.keyword(schema, name).map(|entry| &entry.entry_type) {
+            Some(EntryType::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Resolve a reference within the schema, e.g. `#/$defs/port`.
+    pub fn resolve_ref(&self, reference: &str) -> Option<&Entry> {
+        self.tree.entry_at_pointer(reference.strip_prefix('#')?)
+    }
+
+    /// Follow the `$ref` of a schema, if there is one.
+    pub fn resolve<'a>(&'a self, schema: &'a Entry) -> &'a Entry {
+        (0..MAX_DEPTH)
+            .try_fold(schema, |schema, _| {
+                match self
+                    .keyword_str(schema, "$ref")
+                    .and_then(|r| self.resolve_ref(r))
+                {
+                    Some(target) => Ok(target),
+                    None => Err(schema),
+                }
+            })
+            .unwrap_or_else(|schema| schema)
+    }
+
+    /// Get the schema for the member `key` of an object described by `schema`.
+    pub fn property_schema(&self, schema: &Entry, key: &str) -> Option<&Entry> {
+        let schema = self.resolve(schema);
+        if let Some(property) = self
+            .keyword(schema, "properties")
+            .and_then(|properties| self.keyword(properties, key))
+        {
+            return Some(property);
+        }
+
+        let pattern = self
+            .keyword(schema, "patternProperties")
+            .map(|patterns| self.tree.object_entries(patterns))
+            .unwrap_or_default()
+            .into_iter()
+            .find(|(pattern, _)| self.regex(&pattern.name).is_some_and(|re| re.is_match(key)));
+        match pattern {
+            Some((_, property)) => Some(property),
+            None => self
+                .keyword(schema, "additionalProperties")
+                .filter(|additional| matches!(additional.entry_type, EntryType::JSONObject(_))),
+        }
+    }
+
+    /// Get the schema for the array value at `idx` of an array described by
+    /// `schema`.
+    pub fn item_schema(&self, schema: &Entry, idx: usize) -> Option<&Entry> {
+        let schema = self.resolve(schema);
+        let prefix = self
+            .keyword(schema, "prefixItems")
+            .map(|prefix| self.tree.array_entries(prefix))
+            .unwrap_or_default();
+
+        match prefix.get(idx) {
+            Some(item) => Some(item),
+            None => self
+                .keyword(schema, "items")
+                .filter(|items| matches!(items.entry_type, EntryType::JSONObject(_))),
+        }
+    }
+
+    /// Get the schema for the entry at `path`, starting at the root schema.
+    pub fn schema_at(&self, path: &JsonPath) -> Option<&Entry> {
+        path.0
+            .iter()
+            .try_fold(self.tree.root()?, |schema, segment| match segment {
+                PathSegment::Key(key) => self.property_schema(schema, key),
+                PathSegment::Index(idx) => self.item_schema(schema, *idx),
+            })
+            .map(|schema| self.resolve(schema))
+    }
+
+    /// Validate a tree against the schema.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::{parse_json, schema::Schema};
+    /// let schema = Schema::parse("{ \"properties\": { \"port\": { \"type\": \"integer\" } } }").unwrap();
+    /// let tree = parse_json("{\n  \"port\": \"80\"\n}").unwrap();
+    /// let diagnostics = schema.validate(&tree);
+    /// assert_eq!("Incorrect type. Expected integer but found string.", diagnostics[0].message);
+    /// assert_eq!(1, diagnostics[0].range.start.line);
+    /// ```
+    pub fn validate(&self, tree: &Tree) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        if let (Some(schema), Some(root)) = (self.tree.root(), tree.root()) {
+            self.validate_entry(schema, tree, root, 0, &mut diagnostics);
+        }
+        diagnostics
+    }
+
+    fn is_valid(&self, schema: &Entry, tree: &Tree, entry: &Entry, depth: usize) -> bool {
+        let mut diagnostics = vec![];
+        self.validate_entry(schema, tree, entry, depth, &mut diagnostics);
+        diagnostics.is_empty()
+    }
+
+    fn validate_entry(
+        &self,
+        schema: &Entry,
+        tree: &Tree,
+        entry: &Entry,
+        depth: usize,
+        out: &mut Vec<Diagnostic>,
+    ) {
+        let range = entry.source_range();
+
+        match schema.entry_type {
+            EntryType::Bool(true) => return,
+            EntryType::Bool(false) => {
+                return error(out, range, "Value is not allowed.", "false");
+            }
+            EntryType::JSONObject(_) => {}
+            _ => return,
+        }
+        if depth > MAX_DEPTH {
+            return;
+        }
+
+        if let Some(reference) = self.keyword_str(schema, "$ref") {
+            match self.resolve_ref(reference) {
+                Some(target) => self.validate_entry(target, tree, entry, depth + 1, out),
+                None => error(
+                    out,
+                    range,
+                    &format!("Can not resolve reference \"{}\".", reference),
+                    "$ref",
+                ),
+            }
+        }
+
+        if let Some(types) = self.keyword(schema, "type") {
+            let names: Vec<&str> = match &types.entry_type {
+                EntryType::String(name) => vec![name.as_str()],
+                EntryType::JSONArray(_) => self
+                    .tree
+                    .array_entries(types)
+                    .into_iter()
+                    .filter_map(|name| match &name.entry_type {
+                        EntryType::String(name) => Some(name.as_str()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            if !names.is_empty() && !names.iter().any(|name| matches_type(entry, name)) {
+                error(
+                    out,
+                    range,
+                    &format!(
+                        "Incorrect type. Expected {} but found {}.",
+                        names.join(" or "),
+                        type_name(entry)
+                    ),
+                    "type",
+                );
+            }
+        }
+
+        if let Some(allowed) = self.keyword(schema, "enum") {
+            let values = self.tree.array_entries(allowed);
+            if !values
+                .iter()
+                .any(|value| entries_equal(&self.tree, value, tree, entry))
+            {
+                let names: Vec<String> = values
+                    .iter()
+                    .map(|value| write_entry(&self.tree, value, &WriteOptions::compact()))
+                    .collect();
+                error(
+                    out,
+                    range,
+                    &format!("Value is not accepted. Valid values: {}.", names.join(", ")),
+                    "enum",
+                );
+            }
+        }
+
+        if let Some(constant) = self.keyword(schema, "const") {
+            if !entries_equal(&self.tree, constant, tree, entry) {
+                error(
+                    out,
+                    range,
+                    &format!(
+                        "Value must be {}.",
+                        write_entry(&self.tree, constant, &WriteOptions::compact())
+                    ),
+                    "const",
+                );
+            }
+        }
+
+        let limit = |name: &str| self.keyword(schema, name).and_then(number);
+        if let Some(value) = number(entry) {
+            if let Some(min) = limit("minimum").filter(|min| value < *min) {
+                error(
+                    out,
+                    range,
+                    &format!("Value is below the minimum of {}.", min),
+                    "minimum",
+                );
+            }
+            if let Some(max) = limit("maximum").filter(|max| value > *max) {
+                error(
+                    out,
+                    range,
+                    &format!("Value is above the maximum of {}.", max),
+                    "maximum",
+                );
+            }
+            if let Some(min) = limit("exclusiveMinimum").filter(|min| value <= *min) {
+                error(
+                    out,
+                    range,
+                    &format!("Value must be greater than {}.", min),
+                    "exclusiveMinimum",
+                );
+            }
+            if let Some(max) = limit("exclusiveMaximum").filter(|max| value >= *max) {
+                error(
+                    out,
+                    range,
+                    &format!("Value must be less than {}.", max),
+                    "exclusiveMaximum",
+                );
+            }
+            if let Some(factor) = self
+                .keyword(schema, "multipleOf")
+                .filter(|factor| number(factor).is_some_and(|f| f > 0.0))
+            {
+                if !is_multiple_of(entry, factor) {
+                    error(
+                        out,
+                        range,
+                        &format!(
+                            "Value is not a multiple of {}.",
+                            write_entry(&self.tree, factor, &WriteOptions::compact())
+                        ),
+                        "multipleOf",
+                    );
+                }
+            }
+        }
+
+        let size = |name: &str| self.keyword(schema, name).and_then(count);
+        if let EntryType::String(value) = &entry.entry_type {
+            let length = value.chars().count();
+            if let Some(min) = size("minLength").filter(|min| length < *min) {
+                error(
+                    out,
+                    range,
+                    &format!("String is shorter than the minimum length of {}.", min),
+                    "minLength",
+                );
+            }
+            if let Some(max) = size("maxLength").filter(|max| length > *max) {
+                error(
+                    out,
+                    range,
+                    &format!("String is longer than the maximum length of {}.", max),
+                    "maxLength",
+                );
+            }
+            if let Some(pattern) = self.keyword_str(schema, "pattern") {
+                if let Some(re) = self.regex(pattern) {
+                    if !re.is_match(value) {
+                        error(
+                            out,
+                            range,
+                            &format!("String does not match the pattern \"{}\".", pattern),
+                            "pattern",
+                        );
+                    }
+                }
+            }
+        }
+
+        if let EntryType::JSONArray(_) = entry.entry_type {
+            let values = tree.array_entries(entry);
+            if let Some(min) = size("minItems").filter(|min| values.len() < *min) {
+                error(
+                    out,
+                    range,
+                    &format!("Array has fewer items than the minimum of {}.", min),
+                    "minItems",
+                );
+            }
+            if let Some(max) = size("maxItems").filter(|max| values.len() > *max) {
+                error(
+                    out,
+                    range,
+                    &format!("Array has more items than the maximum of {}.", max),
+                    "maxItems",
+                );
+            }
+            if let Some(EntryType::Bool(true)) =
+                self.keyword(schema, "uniqueItems").map(|e| &e.entry_type)
+            {
+                values.iter().enumerate().for_each(|(idx, value)| {
+                    if values[..idx]
+                        .iter()
+                        .any(|other| entries_equal(tree, other, tree, value))
+                    {
+                        error(
+                            out,
+                            value.source_range(),
+                            "Array has duplicate items.",
+                            "uniqueItems",
+                        );
+                    }
+                });
+            }
+
+            let prefix = self
+                .keyword(schema, "prefixItems")
+                .map(|prefix| self.tree.array_entries(prefix))
+                .unwrap_or_default();
+            values.iter().enumerate().for_each(|(idx, value)| {
+                let item = match prefix.get(idx) {
+                    Some(item) => Some(*item),
+                    None => self.keyword(schema, "items"),
+                };
+                if let Some(item) = item {
+                    self.validate_entry(item, tree, value, depth + 1, out);
+                }
+            });
+        }
+
+        if let EntryType::JSONObject(_) = entry.entry_type {
+            let members = tree.object_entries(entry);
+            // Missing properties are reported at the key of the object.
+            let object_range = entry
+                .key
+                .and_then(|key| tree.keys.get(key))
+                .map_or(range, |key| key.source_range());
+
+            if let Some(min) = size("minProperties").filter(|min| members.len() < *min) {
+                error(
+                    out,
+                    object_range,
+                    &format!("Object has fewer properties than the minimum of {}.", min),
+                    "minProperties",
+                );
+            }
+            if let Some(max) = size("maxProperties").filter(|max| members.len() > *max) {
+                error(
+                    out,
+                    object_range,
+                    &format!("Object has more properties than the maximum of {}.", max),
+                    "maxProperties",
+                );
+            }
+            if let Some(required) = self.keyword(schema, "required") {
+                self.tree
+                    .array_entries(required)
+                    .iter()
+                    .for_each(|name| match &name.entry_type {
+                        EntryType::String(name)
+                            if !members.iter().any(|(k, _)| &k.name == name) =>
+                        {
+                            error(
+                                out,
+                                object_range,
+                                &format!("Missing property \"{}\".", name),
+                                "required",
+                            )
+                        }
+                        _ => {}
+                    });
+            }
+
+            let properties = self.keyword(schema, "properties");
+            let patterns: Vec<(&Regex, &Entry)> = self
+                .keyword(schema, "patternProperties")
+                .map(|patterns| self.tree.object_entries(patterns))
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(pattern, schema)| Some((self.regex(&pattern.name)?, schema)))
+                .collect();
+            let additional = self.keyword(schema, "additionalProperties");
+
+            members.iter().for_each(|(key, value)| {
+                let mut matched = false;
+                if let Some(property) = properties.and_then(|p| self.keyword(p, &key.name)) {
+                    matched = true;
+                    self.validate_entry(property, tree, value, depth + 1, out);
+                }
+                patterns
+                    .iter()
+                    .filter(|(re, _)| re.is_match(&key.name))
+                    .for_each(|(_, property)| {
+                        matched = true;
+                        self.validate_entry(property, tree, value, depth + 1, out);
+                    });
+
+                match additional {
+                    Some(additional) if !matched => {
+                        if let EntryType::Bool(false) = additional.entry_type {
+                            error(
+                                out,
+                                key.source_range(),
+                                &format!("Property \"{}\" is not allowed.", key.name),
+                                "additionalProperties",
+                            );
+                        } else {
+                            self.validate_entry(additional, tree, value, depth + 1, out);
+                        }
+                    }
+                    _ => {}
+                }
+            });
+        }
+
+        let subschemas = |name: &str| {
+            self.keyword(schema, name)
+                .map(|list| self.tree.array_entries(list))
+                .unwrap_or_default()
+        };
+        subschemas("allOf")
+            .iter()
+            .for_each(|sub| self.validate_entry(sub, tree, entry, depth + 1, out));
+
+        let any_of = subschemas("anyOf");
+        if !any_of.is_empty()
+            && !any_of
+                .iter()
+                .any(|sub| self.is_valid(sub, tree, entry, depth + 1))
+        {
+            error(
+                out,
+                range,
+                "Value does not match any schema of \"anyOf\".",
+                "anyOf",
+            );
+        }
+
+        let one_of = subschemas("oneOf");
+        let matching = one_of
+            .iter()
+            .filter(|sub| self.is_valid(sub, tree, entry, depth + 1))
+            .count();
+        if !one_of.is_empty() && matching != 1 {
+            error(
+                out,
+                range,
+                &format!(
+                    "Value matches {} schemas of \"oneOf\", but must match exactly one.",
+                    matching
+                ),
+                "oneOf",
+            );
+        }
+
+        if let Some(not) = self.keyword(schema, "not") {
+            if self.is_valid(not, tree, entry, depth + 1) {
+                error(
+                    out,
+                    range,
+                    "Value must not match the schema of \"not\".",
+                    "not",
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonPath, PathSegment, Schema};
+    use crate::parse_json;
+    use crate::types::Position;
+
+    fn messages(schema: &str, text: &str) -> Vec<(String, String)> {
+        let schema = Schema::parse(schema).unwrap();
+        schema
+            .validate(&parse_json(text).unwrap())
+            .into_iter()
+            .map(|d| (d.code, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_object() {
+        let schema = "{
+  \"type\": \"object\",
+  \"required\": [\"name\", \"port\"],
+  \"properties\": {
+    \"name\": { \"type\": \"string\", \"pattern\": \"^[a-z]+$\" },
+    \"port\": { \"$ref\": \"#/$defs/port\" },
+    \"mode\": { \"enum\": [\"fast\", \"slow\"] }
+  },
+  \"additionalProperties\": false,
+  \"$defs\": { \"port\": { \"type\": \"integer\", \"maximum\": 65535 } }
+}";
+        let text =
+            "{\n  \"name\": \"A1\",\n  \"port\": 70000,\n  \"mode\": \"medium\",\n  \"x\": 1\n}";
+        let tree = parse_json(text).unwrap();
+        let diagnostics = Schema::parse(schema).unwrap().validate(&tree);
+        let codes: Vec<&str> = diagnostics.iter().map(|d| d.code.as_str()).collect();
+        assert_eq!(
+            vec!["pattern", "maximum", "enum", "additionalProperties"],
+            codes
+        );
+        assert_eq!(
+            "Value is not accepted. Valid values: \"fast\", \"slow\".",
+            diagnostics[2].message
+        );
+        assert_eq!(Position::new(4, 2, 57), diagnostics[3].range.start);
+        assert_eq!(Position::new(4, 5, 60), diagnostics[3].range.end);
+
+        assert_eq!(
+            vec![(
+                "required".to_owned(),
+                "Missing property \"port\".".to_owned()
+            )],
+            messages(schema, "{ \"name\": \"a\" }")
+        );
+        assert!(messages(schema, "{ \"name\": \"a\", \"port\": 80.0 }").is_empty());
+    }
+
+    #[test]
+    fn test_validate_combinators() {
+        let schema = "{ \"properties\": {
+  \"a\": { \"anyOf\": [{ \"type\": \"string\" }, { \"minimum\": 10 }] },
+  \"b\": { \"oneOf\": [{ \"type\": \"number\" }, { \"type\": \"integer\" }] },
+  \"c\": { \"items\": { \"type\": \"boolean\" }, \"uniqueItems\": true },
+  \"d\": { \"not\": { \"const\": null } }
+} }";
+        assert!(messages(schema, "{ \"a\": \"x\", \"b\": 1.5, \"c\": [true, false] }").is_empty());
+
+        let codes: Vec<String> = messages(
+            schema,
+            "{ \"a\": 5, \"b\": 1, \"c\": [true, 1, true], \"d\": null }",
+        )
+        .into_iter()
+        .map(|(code, _)| code)
+        .collect();
+        assert_eq!(vec!["anyOf", "oneOf", "uniqueItems", "type", "not"], codes);
+    }
+
+    #[test]
+    fn test_validate_patterns() {
+        let schema = "{
+  \"patternProperties\": {
+    \"^x-\": { \"type\": \"string\" },
+    \"(\": { \"type\": \"null\" }
+  },
+  \"properties\": { \"id\": { \"pattern\": \"[0-9]{3}\" } },
+  \"additionalProperties\": false
+}";
+        let parsed = Schema::parse(schema).unwrap();
+        // The invalid pattern `(` is left out and matches nothing.
+        assert_eq!(2, parsed.patterns.len());
+        assert!(parsed
+            .schema_at(&JsonPath(vec![PathSegment::Key("x-a".to_owned())]))
+            .is_some());
+        assert!(parsed
+            .schema_at(&JsonPath(vec![PathSegment::Key("y".to_owned())]))
+            .is_none());
+
+        assert!(messages(schema, "{ \"x-a\": \"b\", \"id\": \"123\" }").is_empty());
+        let codes: Vec<String> = messages(schema, "{ \"x-a\": 1, \"id\": \"12\", \"(\": null }")
+            .into_iter()
+            .map(|(code, _)| code)
+            .collect();
+        assert_eq!(vec!["type", "pattern", "additionalProperties"], codes);
+    }
+
+    #[test]
+    fn test_validate_multiple_of() {
+        let schema = "{ \"properties\": {
+  \"a\": { \"multipleOf\": 0.1 },
+  \"b\": { \"multipleOf\": 3 }
+} }";
+        assert!(messages(schema, "{ \"a\": 0.3, \"b\": 9007199254740993 }").is_empty());
+        assert!(messages(schema, "{ \"a\": 12.3, \"b\": -6.0 }").is_empty());
+        assert_eq!(
+            vec![
+                (
+                    "multipleOf".to_owned(),
+                    "Value is not a multiple of 0.1.".to_owned()
+                ),
+                (
+                    "multipleOf".to_owned(),
+                    "Value is not a multiple of 3.".to_owned()
+                )
+            ],
+            messages(schema, "{ \"a\": 0.35, \"b\": 9007199254740992 }")
+        );
+    }
+}
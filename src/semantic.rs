@@ -0,0 +1,202 @@
+use super::lex::{lex, TokenKind};
+use super::tree::Tree;
+use super::types::Range;
+use std::collections::HashSet;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SemanticKind {
+    Property,
+    String,
+    Number,
+    Boolean,
+    Null,
+    Punctuation,
+    Comment,
+}
+
+impl SemanticKind {
+    /// All kinds, in the order of their index in the encoded tokens.
+    pub const ALL: [SemanticKind; 7] = [
+        SemanticKind::Property,
+        SemanticKind::String,
+        SemanticKind::Number,
+        SemanticKind::Boolean,
+        SemanticKind::Null,
+        SemanticKind::Punctuation,
+        SemanticKind::Comment,
+    ];
+
+    /// The name of the kind in an LSP token legend. `boolean` and `null` are
+    /// not predefined by LSP, editors usually style them like keywords.
+    pub fn name(self) -> &'static str {
+        match self {
+            SemanticKind::Property => "property",
+            SemanticKind::String => "string",
+            SemanticKind::Number => "number",
+            SemanticKind::Boolean => "boolean",
+            SemanticKind::Null => "null",
+            SemanticKind::Punctuation => "operator",
+            SemanticKind::Comment => "comment",
+        }
+    }
+
+    /// The LSP token legend, with the names of `ALL`.
+    pub fn legend() -> Vec<&'static str> {
+        SemanticKind::ALL.iter().map(|kind| kind.name()).collect()
+    }
+
+    fn index(self) -> u32 {
+        SemanticKind::ALL
+            .iter()
+            .position(|kind| *kind == self)
+            .unwrap_or_default() as u32
+    }
+}
+
+/// A token to highlight, with its range including the quotes of strings.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub range: Range,
+    pub kind: SemanticKind,
+}
+
+/// Get the tokens to highlight in a text and its tree, in source order. Keys of
+/// the tree are told apart from string values, whitespace and invalid input
+/// are left out.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::{parse_json, semantic::{semantic_tokens, SemanticKind}};
+/// let text = "{ \"a\": \"b\" }";
+/// let tree = parse_json(text).unwrap();
+/// let kinds: Vec<SemanticKind> = semantic_tokens(&tree, text)
+///     .iter()
+///     .map(|token| token.kind)
+///     .collect();
+/// assert_eq!(
+///     vec![
+///         SemanticKind::Punctuation,
+///         SemanticKind::Property,
+///         SemanticKind::Punctuation,
+///         SemanticKind::String,
+///         SemanticKind::Punctuation,
+///     ],
+///     kinds
+/// );
+/// ```
+pub fn semantic_tokens(tree: &Tree, text: &str) -> Vec<SemanticToken> {
+    let keys: HashSet<usize> = tree
+        .keys
+        .iter()
+        .map(|key| key.source_range().start.idx)
+        .collect();
+
+    lex(text)
+        .filter_map(|token| {
+            let kind = match token.kind {
+                TokenKind::String if keys.contains(&token.range.start.idx) => {
+                    SemanticKind::Property
+                }
+                TokenKind::String => SemanticKind::String,
+                TokenKind::Number => SemanticKind::Number,
+                TokenKind::Bool => SemanticKind::Boolean,
+                TokenKind::Null => SemanticKind::Null,
+                TokenKind::Comment => SemanticKind::Comment,
+                TokenKind::ObjectOpen
+                | TokenKind::ObjectClose
+                | TokenKind::ArrayOpen
+                | TokenKind::ArrayClose
+                | TokenKind::Comma
+                | TokenKind::Colon => SemanticKind::Punctuation,
+                TokenKind::Whitespace | TokenKind::Error => return None,
+            };
+            Some(SemanticToken {
+                range: token.range,
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// Encode tokens in the relative format of LSP: five numbers per token, the
+/// line delta, the start delta, the length, the index of the kind in
+/// `SemanticKind::ALL` and no modifiers. Starts and lengths are counted in
+/// utf-16 code units of `text`, the text the tokens are from.
+pub fn encode_semantic_tokens(tokens: &[SemanticToken], text: &str) -> Vec<u32> {
+    let utf16_len = |start: usize, end: usize| text[start..end].encode_utf16().count() as u32;
+    let mut last_line = 0;
+    let mut last_start = 0;
+
+    tokens
+        .iter()
+        .flat_map(|token| {
+            let start = token.range.start;
+            let line_start = text[..start.idx].rfind('\n').map_or(0, |pos| pos + 1);
+            let char = utf16_len(line_start, start.idx);
+            let line = start.line as u32;
+            let delta_start = if line == last_line {
+                char - last_start
+            } else {
+                char
+            };
+            let encoded = [
+                line - last_line,
+                delta_start,
+                utf16_len(start.idx, token.range.end.idx),
+                token.kind.index(),
+                0,
+            ];
+            last_line = line;
+            last_start = char;
+            encoded
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_semantic_tokens, semantic_tokens, SemanticKind};
+    use crate::parse_json_tolerant;
+
+    #[test]
+    fn test_semantic_tokens() {
+        let text = "{\n  // 😀\n  \"é\": [1, true, null, \"x\"], \"b\": tru\n}";
+        let tree = parse_json_tolerant(text);
+        let tokens = semantic_tokens(&tree, text);
+
+        let kinds: Vec<(SemanticKind, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.kind, t.range.start.line, t.range.start.char))
+            .collect();
+        assert_eq!(
+            vec![
+                (SemanticKind::Punctuation, 0, 0),
+                (SemanticKind::Comment, 1, 2),
+                (SemanticKind::Property, 2, 2),
+                (SemanticKind::Punctuation, 2, 5),
+                (SemanticKind::Punctuation, 2, 7),
+                (SemanticKind::Number, 2, 8),
+                (SemanticKind::Punctuation, 2, 9),
+                (SemanticKind::Boolean, 2, 11),
+                (SemanticKind::Punctuation, 2, 15),
+                (SemanticKind::Null, 2, 17),
+                (SemanticKind::Punctuation, 2, 21),
+                (SemanticKind::String, 2, 23),
+                (SemanticKind::Punctuation, 2, 26),
+                (SemanticKind::Punctuation, 2, 27),
+                (SemanticKind::Property, 2, 29),
+                (SemanticKind::Punctuation, 2, 32),
+                (SemanticKind::Punctuation, 3, 0),
+            ],
+            kinds
+        );
+
+        let encoded = encode_semantic_tokens(&tokens, text);
+        assert_eq!(tokens.len() * 5, encoded.len());
+        // The comment is 5 utf-16 code units long because of the surrogate pair.
+        assert_eq!(vec![1, 2, 5, 6, 0], encoded[5..10].to_vec());
+        assert_eq!(vec![1, 2, 3, 0, 0], encoded[10..15].to_vec());
+        assert_eq!(vec![0, 3, 1, 5, 0], encoded[15..20].to_vec());
+        assert_eq!(vec![1, 0, 1, 5, 0], encoded[encoded.len() - 5..].to_vec());
+    }
+}
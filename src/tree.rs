@@ -1,5 +1,6 @@
-use super::types::Range;
+use super::types::{ParseError, ParseResult, Position, Range};
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug)]
 pub enum EntryType {
@@ -17,6 +18,9 @@ pub struct Entry {
     pub key: Option<usize>,
     pub range: Range,
     pub entry_type: EntryType,
+    /// The index of the object or array containing the entry, `None` for the
+    /// root and for values replaced by a later duplicate key.
+    pub parent: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -25,10 +29,46 @@ pub struct Key {
     pub range: Range,
 }
 
+/// Extend a range without quotes to the quotes around it.
+fn with_quotes(range: Range) -> Range {
+    Range::new(
+        Position::new(
+            range.start.line,
+            range.start.char.saturating_sub(1),
+            range.start.idx.saturating_sub(1),
+        ),
+        range.end + Position::new(0, 1, 1),
+    )
+}
+
+impl Entry {
+    /// The range of the value in the source. Unlike `range`, it includes the
+    /// quotes of strings.
+    pub fn source_range(&self) -> Range {
+        match self.entry_type {
+            EntryType::String(_) => with_quotes(self.range),
+            _ => self.range,
+        }
+    }
+}
+
+impl Key {
+    /// The range of the key in the source including its quotes.
+    pub fn source_range(&self) -> Range {
+        with_quotes(self.range)
+    }
+}
+
 #[derive(Debug)]
 pub struct Tree {
     pub entries: Vec<Entry>,
     pub keys: Vec<Key>,
+    /// Ranges of the strings without a closing quote, which
+    /// `parse_json_tolerant` closed at the end of their line.
+    pub unclosed: Vec<Range>,
+    /// Ranges of the broken literals, like `tru`, which `parse_json_tolerant`
+    /// skipped.
+    pub invalid: Vec<Range>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,7 +79,439 @@ pub enum PathType<'input> {
     RecursiveWildcard,
 }
 
+/// A step of an owned json path, the position of a value in a tree.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct JsonPath(pub Vec<PathSegment>);
+
+impl JsonPath {
+    pub fn with_key(&self, key: &str) -> JsonPath {
+        let mut path = self.clone();
+        path.0.push(PathSegment::Key(key.to_owned()));
+        path
+    }
+
+    pub fn with_index(&self, idx: usize) -> JsonPath {
+        let mut path = self.clone();
+        path.0.push(PathSegment::Index(idx));
+        path
+    }
+
+    /// Format the path as json pointer (RFC 6901), e.g. `/servers/0/port`.
+    pub fn to_pointer(&self) -> String {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+                PathSegment::Index(idx) => format!("/{}", idx),
+            })
+            .collect()
+    }
+
+    /// Get the path as query for `Tree::value_at`.
+    pub fn as_path_types(&self) -> Vec<PathType<'_>> {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) => PathType::Object(key),
+                PathSegment::Index(idx) => PathType::Array(*idx),
+            })
+            .collect()
+    }
+}
+
+/// A step of a parsed path query, owning its keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuerySegment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+    RecursiveWildcard,
+}
+
+/// A path query parsed from text, e.g. `$.servers[*].port` or `$..port`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathQuery(pub Vec<QuerySegment>);
+
+impl PathQuery {
+    /// Parse a JSONPath with member names (`.a`, `['a b']`), indices (`[0]`),
+    /// wildcards (`.*`, `[*]`) and descendants (`..a`). The leading `$` is
+    /// optional.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::{parse_json, tree::PathQuery};
+    /// let tree = parse_json("{ \"a\": [{ \"b\": 1 }, { \"b\": 2 }] }").unwrap();
+    /// let query = PathQuery::parse("$.a[*].b").unwrap();
+    /// assert_eq!(2, tree.value_at(&query.as_path_types()).len());
+    /// ```
+    pub fn parse(query: &str) -> ParseResult<PathQuery> {
+        let mut segments = vec![];
+        let mut rest = query.strip_prefix('$').unwrap_or(query);
+        let offset = |rest: &str| query.len() - rest.len();
+
+        while !rest.is_empty() {
+            if let Some(next) = rest.strip_prefix("..") {
+                segments.push(QuerySegment::RecursiveWildcard);
+                rest = next;
+                if rest.starts_with('[') {
+                    continue;
+                }
+            } else if let Some(next) = rest.strip_prefix('.') {
+                rest = next;
+            } else if let Some(next) = rest.strip_prefix('[') {
+                let (segment, next) =
+                    parse_bracket(next).ok_or(ParseError::InvalidPath(offset(rest)))?;
+                segments.push(segment);
+                rest = next;
+                continue;
+            } else if !segments.is_empty() || rest.len() != query.len() {
+                return Err(ParseError::InvalidPath(offset(rest)));
+            }
+
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            match &rest[..end] {
+                name if name.is_empty() || name.contains(']') => {
+                    return Err(ParseError::InvalidPath(offset(rest)))
+                }
+                "*" => segments.push(QuerySegment::Wildcard),
+                name => segments.push(QuerySegment::Key(name.to_owned())),
+            }
+            rest = &rest[end..];
+        }
+
+        Ok(PathQuery(segments))
+    }
+
+    /// Check whether the query matches a path, e.g. `$..port` matches
+    /// `$.servers[0].port`.
+    pub fn matches(&self, path: &JsonPath) -> bool {
+        fn matches(query: &[QuerySegment], path: &[PathSegment]) -> bool {
+            match (query.first(), path.first()) {
+                (None, None) => true,
+                (Some(QuerySegment::RecursiveWildcard), _) => {
+                    matches(&query[1..], path) || (!path.is_empty() && matches(query, &path[1..]))
+                }
+                (Some(QuerySegment::Wildcard), Some(_)) => matches(&query[1..], &path[1..]),
+                (Some(QuerySegment::Key(a)), Some(PathSegment::Key(b))) if a == b => {
+                    matches(&query[1..], &path[1..])
+                }
+                (Some(QuerySegment::Index(a)), Some(PathSegment::Index(b))) if a == b => {
+                    matches(&query[1..], &path[1..])
+                }
+                _ => false,
+            }
+        }
+
+        matches(&self.0, &path.0)
+    }
+
+    /// Get the query for `Tree::value_at` and `Tree::keys_at`.
+    pub fn as_path_types(&self) -> Vec<PathType<'_>> {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                QuerySegment::Key(key) => PathType::Object(key),
+                QuerySegment::Index(idx) => PathType::Array(*idx),
+                QuerySegment::Wildcard => PathType::Wildcard,
+                QuerySegment::RecursiveWildcard => PathType::RecursiveWildcard,
+            })
+            .collect()
+    }
+}
+
+/// Parse the inside of a bracket step, e.g. `0]`, `*]` or `'a b']`.
+fn parse_bracket(text: &str) -> Option<(QuerySegment, &str)> {
+    let quote = text.chars().next().filter(|c| *c == '\'' || *c == '"');
+    let quote = match quote {
+        Some(quote) => quote,
+        None => {
+            let end = text.find(']')?;
+            let segment = match text[..end].trim() {
+                "*" => QuerySegment::Wildcard,
+                idx => QuerySegment::Index(idx.parse().ok()?),
+            };
+            return Some((segment, &text[end + 1..]));
+        }
+    };
+
+    let mut name = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => name.push(chars.next()?.1),
+            c if c == quote => {
+                let rest = text[idx + 1..].strip_prefix(']')?;
+                return Some((QuerySegment::Key(name), rest));
+            }
+            c => name.push(c),
+        }
+    }
+    None
+}
+
+// Formats the path like `$.servers[0].port` or `$['a key']`.
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "$")?;
+        self.0.iter().try_for_each(|segment| match segment {
+            PathSegment::Key(key)
+                if !key.is_empty()
+                    && !key.starts_with(|c: char| c.is_ascii_digit())
+                    && key.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                write!(f, ".{}", key)
+            }
+            PathSegment::Key(key) => {
+                write!(f, "['{}']", key.replace('\\', "\\\\").replace('\'', "\\'"))
+            }
+            PathSegment::Index(idx) => write!(f, "[{}]", idx),
+        })
+    }
+}
+
+pub(crate) fn number(entry: &Entry) -> Option<f64> {
+    match entry.entry_type {
+        EntryType::Int(value) => Some(value as f64),
+        EntryType::Float(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Compare two entries of possibly different trees by value.
+pub fn entries_equal(a_tree: &Tree, a: &Entry, b_tree: &Tree, b: &Entry) -> bool {
+    match (&a.entry_type, &b.entry_type) {
+        (EntryType::JSONObject(a_hash), EntryType::JSONObject(b_hash)) => {
+            a_hash.len() == b_hash.len()
+                && a_hash.iter().all(|(key, (_, a_value))| {
+                    b_hash.get(key).is_some_and(|(_, b_value)| {
+                        entries_equal(
+                            a_tree,
+                            &a_tree.entries[*a_value],
+                            b_tree,
+                            &b_tree.entries[*b_value],
+                        )
+                    })
+                })
+        }
+        (EntryType::JSONArray(a_array), EntryType::JSONArray(b_array)) => {
+            a_array.len() == b_array.len()
+                && a_array.iter().zip(b_array).all(|(a_value, b_value)| {
+                    entries_equal(
+                        a_tree,
+                        &a_tree.entries[*a_value],
+                        b_tree,
+                        &b_tree.entries[*b_value],
+                    )
+                })
+        }
+        (EntryType::String(a), EntryType::String(b)) => a == b,
+        (EntryType::Bool(a), EntryType::Bool(b)) => a == b,
+        (EntryType::Null, EntryType::Null) => true,
+        // Integers past 2^53 are not exact as f64.
+        (EntryType::Int(a), EntryType::Int(b)) => a == b,
+        _ => match (number(a), number(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        },
+    }
+}
+
 impl Tree {
+    /// The root object of the tree. It is always the last entry.
+    pub fn root(&self) -> Option<&Entry> {
+        self.entries.last()
+    }
+
+    /// Get the keys and values of an object entry in source order.
+    pub fn object_entries(&self, entry: &Entry) -> Vec<(&Key, &Entry)> {
+        let mut members: Vec<(usize, usize)> = match &entry.entry_type {
+            EntryType::JSONObject(hash) => hash.values().cloned().collect(),
+            _ => vec![],
+        };
+        members.sort_unstable();
+
+        members
+            .into_iter()
+            .filter_map(|(key, value)| Some((self.keys.get(key)?, self.entries.get(value)?)))
+            .collect()
+    }
+
+    /// Get the values of an array entry in source order.
+    pub fn array_entries(&self, entry: &Entry) -> Vec<&Entry> {
+        match &entry.entry_type {
+            EntryType::JSONArray(array) => array
+                .iter()
+                .filter_map(|value| self.entries.get(*value))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The range of a string with its quotes, without the closing one if the
+    /// string is unclosed.
+    fn quoted(&self, range: Range) -> Range {
+        let quoted = with_quotes(range);
+        if self.unclosed.contains(&range) {
+            Range::new(quoted.start, range.end)
+        } else {
+            quoted
+        }
+    }
+
+    /// The range of a value in the source like `Entry::source_range`, which
+    /// also knows about unclosed strings of a tolerant parse.
+    pub fn entry_source_range(&self, entry: &Entry) -> Range {
+        match entry.entry_type {
+            EntryType::String(_) => self.quoted(entry.range),
+            _ => entry.range,
+        }
+    }
+
+    /// The range of a key in the source like `Key::source_range`, which also
+    /// knows about unclosed strings of a tolerant parse.
+    pub fn key_source_range(&self, key: &Key) -> Range {
+        self.quoted(key.range)
+    }
+
+    /// Get the object or array containing an entry.
+    pub fn parent(&self, entry: &Entry) -> Option<&Entry> {
+        entry.parent.and_then(|parent| self.entries.get(parent))
+    }
+
+    /// Set the `parent` of the values of every object and array.
+    pub(crate) fn link_parents(&mut self) {
+        for idx in 0..self.entries.len() {
+            let children: Vec<usize> = match &self.entries[idx].entry_type {
+                EntryType::JSONObject(hash) => hash.values().map(|(_, value)| *value).collect(),
+                EntryType::JSONArray(array) => array.clone(),
+                _ => continue,
+            };
+            children.into_iter().for_each(|child| {
+                if let Some(child) = self.entries.get_mut(child) {
+                    child.parent = Some(idx);
+                }
+            });
+        }
+    }
+
+    /// Get the innermost object or array around a position, i.e. the position
+    /// is between its brackets. Containers ending at the end of the root also
+    /// contain the position right after them, as the tolerant parser closes
+    /// unclosed containers there.
+    pub fn container_at(&self, position: Position) -> Option<&Entry> {
+        let root_end = self.root()?.range.end.idx;
+
+        self.entries
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry.entry_type,
+                    EntryType::JSONObject(_) | EntryType::JSONArray(_)
+                )
+            })
+            .filter(|entry| {
+                let (start, end) = (entry.range.start.idx, entry.range.end.idx);
+                start < position.idx
+                    && (position.idx < end || (position.idx == end && end == root_end))
+            })
+            .max_by_key(|entry| entry.range.start.idx)
+    }
+
+    /// Get the index in `entries` of the entry at a json pointer (RFC 6901).
+    pub fn index_at_pointer(&self, pointer: &str) -> Option<usize> {
+        let root = self.entries.len().checked_sub(1)?;
+        if pointer.is_empty() {
+            return Some(root);
+        }
+
+        pointer
+            .strip_prefix('/')?
+            .split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .try_fold(root, |idx, token| {
+                match &self.entries.get(idx)?.entry_type {
+                    EntryType::JSONObject(hash) => hash.get(&token).map(|(_, value)| *value),
+                    EntryType::JSONArray(array) => token
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|idx| array.get(idx))
+                        .copied(),
+                    _ => None,
+                }
+            })
+    }
+
+    /// Get the entry at a json pointer (RFC 6901), e.g. `/servers/0/port`.
+    pub fn entry_at_pointer(&self, pointer: &str) -> Option<&Entry> {
+        self.index_at_pointer(pointer)
+            .and_then(|idx| self.entries.get(idx))
+    }
+
+    /// Get the path from the root to an entry of this tree.
+    pub fn path_of(&self, entry: &Entry) -> JsonPath {
+        let mut segments = vec![];
+        let mut current = entry;
+
+        while let Some(parent_idx) = current.parent {
+            let parent = &self.entries[parent_idx];
+            match (
+                &parent.entry_type,
+                current.key.and_then(|key| self.keys.get(key)),
+            ) {
+                (EntryType::JSONObject(_), Some(key)) => {
+                    segments.push(PathSegment::Key(key.name.clone()))
+                }
+                (EntryType::JSONArray(array), _) => {
+                    let pos = array
+                        .iter()
+                        .position(|value| std::ptr::eq(&self.entries[*value], current));
+                    segments.push(PathSegment::Index(pos.unwrap_or_default()));
+                }
+                _ => {}
+            }
+            current = parent;
+        }
+
+        segments.reverse();
+        JsonPath(segments)
+    }
+
+    /// Iterate over all entries with their paths, depth first in source order,
+    /// starting with the root.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::parse_json;
+    /// let tree = parse_json("{ \"a\": [1, { \"b\": null }] }").unwrap();
+    /// let paths: Vec<String> = tree.iter().map(|(path, _)| path.to_string()).collect();
+    /// assert_eq!(vec!["$", "$.a", "$.a[0]", "$.a[1]", "$.a[1].b"], paths);
+    /// ```
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            tree: self,
+            stack: self
+                .root()
+                .map(|root| (JsonPath::default(), root))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Iterate over an entry and everything inside of it, with their paths
+    /// in this tree.
+    pub fn iter_from<'a>(&'a self, entry: &'a Entry) -> Iter<'a> {
+        Iter {
+            tree: self,
+            stack: vec![(self.path_of(entry), entry)],
+        }
+    }
+
     fn handle_path(&self, entries: &[&Entry], path: &str) -> Vec<&Entry> {
         entries
             .iter()
@@ -131,10 +603,11 @@ impl Tree {
     ///
     /// **Example**
     /// ```
-    /// let text = "{ \"a\": { \"b\": ["c"] } }";
+    /// # use json_position_parser::{parse_json, tree::PathType};
+    /// let text = "{ \"a\": { \"b\": [\"c\"] } }";
     /// match parse_json(&text) {
     ///     Ok(tree) => {
-    ///         let keys = tree.values_at(&[
+    ///         let values = tree.value_at(&[
     ///            PathType::Object("a"),
     ///            PathType::Object("b"),
     ///            PathType::Array(0),
@@ -164,7 +637,8 @@ impl Tree {
     ///
     /// **Example**
     /// ```
-    /// let text = "{ \"a\": { \"b\": ["c"] } }";
+    /// # use json_position_parser::{parse_json, tree::PathType};
+    /// let text = "{ \"a\": { \"b\": [\"c\"] } }";
     /// match parse_json(&text) {
     ///     Ok(tree) => {
     ///         let keys = tree.keys_at(&[
@@ -196,3 +670,46 @@ impl Tree {
             .collect::<Vec<&Key>>()
     }
 }
+
+/// A depth first iterator over entries and their paths. It keeps the entries
+/// still to visit on the heap, so deeply nested trees do not overflow the
+/// stack.
+pub struct Iter<'a> {
+    tree: &'a Tree,
+    stack: Vec<(JsonPath, &'a Entry)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (JsonPath, &'a Entry);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, entry) = self.stack.pop()?;
+        let tree = self.tree;
+        match entry.entry_type {
+            EntryType::JSONObject(_) => self.stack.extend(
+                tree.object_entries(entry)
+                    .into_iter()
+                    .rev()
+                    .map(|(key, value)| (path.with_key(&key.name), value)),
+            ),
+            EntryType::JSONArray(_) => self.stack.extend(
+                tree.array_entries(entry)
+                    .into_iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(idx, value)| (path.with_index(idx), value)),
+            ),
+            _ => {}
+        }
+        Some((path, entry))
+    }
+}
+
+impl<'a> IntoIterator for &'a Tree {
+    type Item = (JsonPath, &'a Entry);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
@@ -6,6 +6,12 @@ pub enum ParseError {
     MissingArrayBrace,
     InvalidType,
     FileNotFound,
+    PathNotFound,
+    UnexpectedCharacter(Range),
+    UnterminatedLiteral(Range),
+    TrailingContent(Range),
+    /// A path query could not be parsed, with the byte offset of the problem.
+    InvalidPath(usize),
     Error,
 }
 
@@ -16,6 +22,17 @@ impl fmt::Display for ParseError {
             ParseError::MissingArrayBrace => write!(f, "Missing array brace"),
             ParseError::MissingObjectBrace => write!(f, "Missing object brace"),
             ParseError::FileNotFound => write!(f, "File not found"),
+            ParseError::PathNotFound => write!(f, "Path not found"),
+            ParseError::UnexpectedCharacter(range) => {
+                write!(f, "Unexpected character at {}", range.start)
+            }
+            ParseError::UnterminatedLiteral(range) => {
+                write!(f, "Unterminated literal at {}", range.start)
+            }
+            ParseError::TrailingContent(range) => {
+                write!(f, "Unexpected content after root value at {}", range.start)
+            }
+            ParseError::InvalidPath(idx) => write!(f, "Invalid path query at offset {}", idx),
             ParseError::Error => write!(f, "Could not parse json"),
         }
     }
@@ -29,7 +46,7 @@ impl error::Error for ParseError {
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 pub struct Range {
     pub start: Position,
     pub end: Position,
@@ -41,20 +58,20 @@ impl Range {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 pub struct Position {
     pub line: usize,
     pub char: usize,
+    /// Byte offset into the parsed text, so `&text[range.start.idx..range.end.idx]`
+    /// slices a range. `char` counts characters within the line instead.
+    /// Before 0.1.0, `idx` counted characters of the text.
     pub idx: usize,
 }
 
-impl Default for Position {
-    fn default() -> Position {
-        Position {
-            line: 0,
-            char: 0,
-            idx: 0,
-        }
+// Lines and chars are zero based, the displayed position is one based.
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.char + 1)
     }
 }
 
@@ -74,3 +91,65 @@ impl Add for Position {
         }
     }
 }
+
+/// Replace the text at `range` with `new_text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub range: Range,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range, new_text: &str) -> TextEdit {
+        TextEdit {
+            range,
+            new_text: new_text.to_owned(),
+        }
+    }
+
+    /// Apply non overlapping edits to the text they were created for.
+    pub fn apply(text: &str, edits: &[TextEdit]) -> String {
+        let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+        sorted.sort_by_key(|edit| (edit.range.start.idx, edit.range.end.idx));
+
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+        sorted.iter().for_each(|edit| {
+            result.push_str(&text[last..edit.range.start.idx]);
+            result.push_str(&edit.new_text);
+            last = edit.range.end.idx;
+        });
+        result.push_str(&text[last..]);
+
+        result
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// A problem found in a json text, e.g. by schema validation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Severity,
+    pub message: String,
+    /// What reported the diagnostic, e.g. the failing schema keyword.
+    pub code: String,
+}
+
+impl Diagnostic {
+    pub fn new(range: Range, severity: Severity, message: &str, code: &str) -> Diagnostic {
+        Diagnostic {
+            range,
+            severity,
+            message: message.to_owned(),
+            code: code.to_owned(),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use super::tree::{Entry, EntryType, JsonPath, Key, Tree};
+use super::types::{ParseError, ParseResult, Range};
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+
+/// Ranges of the values of a tree by their json pointer, e.g. `/servers/0`.
+/// The ranges include the quotes of strings.
+pub type PositionMap = HashMap<String, Range>;
+
+impl Tree {
+    fn convert(&self, entry: &Entry, path: &JsonPath, positions: &mut PositionMap) -> Value {
+        positions.insert(path.to_pointer(), entry.source_range());
+
+        match &entry.entry_type {
+            EntryType::JSONObject(_) => Value::Object(
+                self.object_entries(entry)
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let value = self.convert(value, &path.with_key(&key.name), positions);
+                        (key.name.clone(), value)
+                    })
+                    .collect::<Map<String, Value>>(),
+            ),
+            EntryType::JSONArray(_) => Value::Array(
+                self.array_entries(entry)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, value)| self.convert(value, &path.with_index(idx), positions))
+                    .collect(),
+            ),
+            EntryType::String(value) => Value::String(value.clone()),
+            EntryType::Int(value) => Value::Number((*value).into()),
+            EntryType::Float(value) => Number::from_f64(*value).map_or(Value::Null, Value::Number),
+            EntryType::Bool(value) => Value::Bool(*value),
+            EntryType::Null => Value::Null,
+        }
+    }
+
+    /// Convert an entry and its children to a `serde_json::Value`.
+    pub fn entry_to_value(&self, entry: &Entry) -> Value {
+        self.convert(entry, &self.path_of(entry), &mut HashMap::new())
+    }
+
+    /// Convert the tree to a `serde_json::Value`.
+    pub fn to_value(&self) -> Value {
+        self.to_value_with_positions().0
+    }
+
+    /// Convert the tree to a `serde_json::Value` and get the range of every
+    /// value by its json pointer.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::parse_json;
+    /// let tree = parse_json("{\n  \"a\": [1, true]\n}").unwrap();
+    /// let (value, positions) = tree.to_value_with_positions();
+    /// assert_eq!(Some(true), value["a"][1].as_bool());
+    /// assert_eq!(1, positions["/a/1"].start.line);
+    /// ```
+    pub fn to_value_with_positions(&self) -> (Value, PositionMap) {
+        let mut positions = HashMap::new();
+        let value = self.root().map_or(Value::Null, |root| {
+            self.convert(root, &JsonPath::default(), &mut positions)
+        });
+        (value, positions)
+    }
+
+    fn push_value(&mut self, key: Option<usize>, value: &Value) -> usize {
+        let entry_type = match value {
+            Value::Object(map) => EntryType::JSONObject(
+                map.iter()
+                    .map(|(name, value)| {
+                        self.keys.push(Key {
+                            name: name.clone(),
+                            range: Range::default(),
+                        });
+                        let key = self.keys.len() - 1;
+                        (name.clone(), (key, self.push_value(Some(key), value)))
+                    })
+                    .collect(),
+            ),
+            Value::Array(values) => EntryType::JSONArray(
+                values
+                    .iter()
+                    .map(|value| self.push_value(None, value))
+                    .collect(),
+            ),
+            Value::String(value) => EntryType::String(value.clone()),
+            Value::Number(number) => match number.as_i64() {
+                Some(int) => EntryType::Int(int),
+                None => EntryType::Float(number.as_f64().unwrap_or_default()),
+            },
+            Value::Bool(value) => EntryType::Bool(*value),
+            Value::Null => EntryType::Null,
+        };
+
+        self.entries.push(Entry {
+            key,
+            range: Range::default(),
+            entry_type,
+            parent: None,
+        });
+        self.entries.len() - 1
+    }
+
+    /// Build a tree from a `serde_json::Value` object. The entries and keys of
+    /// the tree have empty ranges, as there is no source text.
+    pub fn from_value(value: &Value) -> ParseResult<Tree> {
+        if !value.is_object() {
+            return Err(ParseError::InvalidType);
+        }
+
+        let mut tree = Tree {
+            entries: vec![],
+            keys: vec![],
+            unclosed: vec![],
+            invalid: vec![],
+        };
+        tree.push_value(None, value);
+        tree.link_parents();
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_json;
+    use crate::tree::{PathType, Tree};
+    use crate::types::Position;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_value() {
+        let text = "{\n  \"a/b\": { \"c\": [1, 2.5, \"x\", null] },\n  \"d\": false\n}";
+        let tree = parse_json(text).unwrap();
+        let (value, positions) = tree.to_value_with_positions();
+
+        assert_eq!(
+            json!({ "a/b": { "c": [1, 2.5, "x", null] }, "d": false }),
+            value
+        );
+        assert_eq!(Position::new(1, 25, 27), positions["/a~1b/c/2"].start);
+        assert_eq!(Position::new(1, 28, 30), positions["/a~1b/c/2"].end);
+        assert_eq!(Position::new(0, 0, 0), positions[""].start);
+        assert_eq!(8, positions.len());
+    }
+
+    #[test]
+    fn test_from_value() {
+        let value = json!({ "a": [1, { "b": 1e100 }], "c": "d" });
+        let tree = Tree::from_value(&value).unwrap();
+
+        let res = tree.value_at(&[
+            PathType::Object("a"),
+            PathType::Array(1),
+            PathType::Object("b"),
+        ]);
+        assert_eq!(1, res.len());
+        assert_eq!(value, tree.to_value());
+        assert!(Tree::from_value(&json!([1])).is_err());
+    }
+}
@@ -0,0 +1,289 @@
+use super::tree::{Entry, EntryType, JsonPath, Key, Tree};
+use super::types::Range;
+
+/// What to do after a callback of a `Visitor`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Visit {
+    Continue,
+    /// Skip the contents of the object or array just entered, or the value of
+    /// the key just visited. The matching `leave_*` callback is still called.
+    Skip,
+    /// End the walk without calling any other callback.
+    Stop,
+}
+
+/// Callbacks for `Tree::walk`, called in source order. The path and depth are
+/// the ones of the value, the root has an empty path and a depth of 0. For
+/// keys they are the ones of the member value. Ranges are source ranges, so
+/// they include the quotes of strings and keys. All callbacks continue the
+/// walk by default.
+pub trait Visitor {
+    fn enter_object(
+        &mut self,
+        _entry: &Entry,
+        _path: &JsonPath,
+        _depth: usize,
+        _range: Range,
+    ) -> Visit {
+        Visit::Continue
+    }
+
+    fn leave_object(
+        &mut self,
+        _entry: &Entry,
+        _path: &JsonPath,
+        _depth: usize,
+        _range: Range,
+    ) -> Visit {
+        Visit::Continue
+    }
+
+    fn enter_array(
+        &mut self,
+        _entry: &Entry,
+        _path: &JsonPath,
+        _depth: usize,
+        _range: Range,
+    ) -> Visit {
+        Visit::Continue
+    }
+
+    fn leave_array(
+        &mut self,
+        _entry: &Entry,
+        _path: &JsonPath,
+        _depth: usize,
+        _range: Range,
+    ) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for every object member before its value.
+    fn visit_key(&mut self, _key: &Key, _path: &JsonPath, _depth: usize, _range: Range) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for strings, numbers, booleans and null.
+    fn visit_value(
+        &mut self,
+        _entry: &Entry,
+        _path: &JsonPath,
+        _depth: usize,
+        _range: Range,
+    ) -> Visit {
+        Visit::Continue
+    }
+}
+
+impl Tree {
+    /// Walk the whole tree with a visitor.
+    ///
+    /// **Example**
+    /// ```
+    /// # use json_position_parser::{parse_json, tree::{Entry, JsonPath}, types::Range};
+    /// # use json_position_parser::visit::{Visit, Visitor};
+    /// struct Strings(Vec<String>);
+    ///
+    /// impl Visitor for Strings {
+    ///     fn visit_value(&mut self, _: &Entry, path: &JsonPath, _: usize, _: Range) -> Visit {
+    ///         self.0.push(path.to_string());
+    ///         Visit::Continue
+    ///     }
+    /// }
+    ///
+    /// let tree = parse_json("{ \"a\": [1, { \"b\": null }] }").unwrap();
+    /// let mut strings = Strings(vec![]);
+    /// tree.walk(&mut strings);
+    /// assert_eq!(vec!["$.a[0]", "$.a[1].b"], strings.0);
+    /// ```
+    pub fn walk<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        if let Some(root) = self.root() {
+            self.walk_entry(root, &JsonPath::default(), 0, visitor);
+        }
+    }
+
+    /// Walk the subtree of an entry with a visitor, starting with the path and
+    /// depth of the entry in this tree.
+    pub fn walk_from<V: Visitor + ?Sized>(&self, entry: &Entry, visitor: &mut V) {
+        let path = self.path_of(entry);
+        let depth = path.0.len();
+        self.walk_entry(entry, &path, depth, visitor);
+    }
+
+    /// Returns false if the visitor stopped the walk.
+    fn walk_entry<V: Visitor + ?Sized>(
+        &self,
+        entry: &Entry,
+        path: &JsonPath,
+        depth: usize,
+        visitor: &mut V,
+    ) -> bool {
+        let range = entry.source_range();
+        match entry.entry_type {
+            EntryType::JSONObject(_) => {
+                match visitor.enter_object(entry, path, depth, range) {
+                    Visit::Stop => return false,
+                    Visit::Skip => {}
+                    Visit::Continue => {
+                        for (key, value) in self.object_entries(entry) {
+                            let path = path.with_key(&key.name);
+                            match visitor.visit_key(key, &path, depth + 1, key.source_range()) {
+                                Visit::Stop => return false,
+                                Visit::Skip => {}
+                                Visit::Continue => {
+                                    if !self.walk_entry(value, &path, depth + 1, visitor) {
+                                        return false;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                visitor.leave_object(entry, path, depth, range) != Visit::Stop
+            }
+            EntryType::JSONArray(_) => {
+                match visitor.enter_array(entry, path, depth, range) {
+                    Visit::Stop => return false,
+                    Visit::Skip => {}
+                    Visit::Continue => {
+                        for (idx, value) in self.array_entries(entry).into_iter().enumerate() {
+                            if !self.walk_entry(value, &path.with_index(idx), depth + 1, visitor) {
+                                return false;
+                            }
+                        }
+                    }
+                }
+                visitor.leave_array(entry, path, depth, range) != Visit::Stop
+            }
+            _ => visitor.visit_value(entry, path, depth, range) != Visit::Stop,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Visit, Visitor};
+    use crate::parse_json;
+    use crate::tree::{Entry, JsonPath, Key};
+    use crate::types::Range;
+
+    /// Records the callbacks, skipping `skip` and stopping at `stop`.
+    struct Recorder {
+        events: Vec<String>,
+        skip: &'static str,
+        stop: &'static str,
+    }
+
+    impl Recorder {
+        fn new(skip: &'static str, stop: &'static str) -> Recorder {
+            Recorder {
+                events: vec![],
+                skip,
+                stop,
+            }
+        }
+
+        fn record(&mut self, event: &str, path: &JsonPath, depth: usize, range: Range) -> Visit {
+            let path = path.to_string();
+            self.events
+                .push(format!("{} {} {} {}", event, path, depth, range.start.idx));
+            match path.as_str() {
+                p if p == self.stop => Visit::Stop,
+                p if p == self.skip => Visit::Skip,
+                _ => Visit::Continue,
+            }
+        }
+    }
+
+    impl Visitor for Recorder {
+        fn enter_object(
+            &mut self,
+            _: &Entry,
+            path: &JsonPath,
+            depth: usize,
+            range: Range,
+        ) -> Visit {
+            self.record("enter_object", path, depth, range)
+        }
+
+        fn leave_object(
+            &mut self,
+            _: &Entry,
+            path: &JsonPath,
+            depth: usize,
+            range: Range,
+        ) -> Visit {
+            self.events
+                .push(format!("leave_object {} {} {}", path, depth, range.end.idx));
+            Visit::Continue
+        }
+
+        fn enter_array(&mut self, _: &Entry, path: &JsonPath, depth: usize, range: Range) -> Visit {
+            self.record("enter_array", path, depth, range)
+        }
+
+        fn leave_array(&mut self, _: &Entry, path: &JsonPath, depth: usize, range: Range) -> Visit {
+            self.events
+                .push(format!("leave_array {} {} {}", path, depth, range.end.idx));
+            Visit::Continue
+        }
+
+        fn visit_key(&mut self, _: &Key, path: &JsonPath, depth: usize, range: Range) -> Visit {
+            self.record("visit_key", path, depth, range)
+        }
+
+        fn visit_value(&mut self, _: &Entry, path: &JsonPath, depth: usize, range: Range) -> Visit {
+            self.record("visit_value", path, depth, range)
+        }
+    }
+
+    const TEXT: &str = "{ \"a\": [1, \"x\"], \"b\": { \"c\": true }, \"d\": null }";
+
+    #[test]
+    fn test_walk() {
+        let tree = parse_json(TEXT).unwrap();
+        let mut recorder = Recorder::new("", "");
+        tree.walk(&mut recorder);
+        assert_eq!(
+            vec![
+                "enter_object $ 0 0",
+                "visit_key $.a 1 2",
+                "enter_array $.a 1 7",
+                "visit_value $.a[0] 2 8",
+                "visit_value $.a[1] 2 11",
+                "leave_array $.a 1 15",
+                "visit_key $.b 1 17",
+                "enter_object $.b 1 22",
+                "visit_key $.b.c 2 24",
+                "visit_value $.b.c 2 29",
+                "leave_object $.b 1 35",
+                "visit_key $.d 1 37",
+                "visit_value $.d 1 42",
+                "leave_object $ 0 48",
+            ],
+            recorder.events
+        );
+
+        let entry = tree.entry_at_pointer("/b").unwrap();
+        let mut recorder = Recorder::new("", "");
+        tree.walk_from(entry, &mut recorder);
+        assert_eq!("visit_key $.b.c 2 24", recorder.events[1]);
+    }
+
+    #[test]
+    fn test_walk_skip_and_stop() {
+        let tree = parse_json(TEXT).unwrap();
+        let mut recorder = Recorder::new("$.a", "$.b.c");
+        tree.walk(&mut recorder);
+        assert_eq!(
+            vec![
+                "enter_object $ 0 0",
+                "visit_key $.a 1 2",
+                "visit_key $.b 1 17",
+                "enter_object $.b 1 22",
+                "visit_key $.b.c 2 24",
+            ],
+            recorder.events
+        );
+    }
+}
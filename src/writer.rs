@@ -0,0 +1,204 @@
+use super::tree::{Entry, EntryType, Tree};
+
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// Put every value on its own line, indented by `indent` per level.
+    pub pretty: bool,
+    pub indent: String,
+    /// Sort object keys alphabetically instead of keeping the source order.
+    pub sort_keys: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions::compact()
+    }
+}
+
+impl WriteOptions {
+    pub fn compact() -> WriteOptions {
+        WriteOptions {
+            pretty: false,
+            indent: String::new(),
+            sort_keys: false,
+        }
+    }
+
+    pub fn pretty(indent: &str) -> WriteOptions {
+        WriteOptions {
+            pretty: true,
+            indent: indent.to_owned(),
+            sort_keys: false,
+        }
+    }
+}
+
+/// Quote and escape a string, so it can be used as json key or value.
+pub fn quote_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    value.chars().for_each(|c| match c {
+        '"' => result.push_str("\\\""),
+        '\\' => result.push_str("\\\\"),
+        '\n' => result.push_str("\\n"),
+        '\r' => result.push_str("\\r"),
+        '\t' => result.push_str("\\t"),
+        '\u{8}' => result.push_str("\\b"),
+        '\u{c}' => result.push_str("\\f"),
+        c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+        c => result.push(c),
+    });
+    result.push('"');
+    result
+}
+
+/// Format a float so it is read back as float. Json has no representation
+/// for `NaN` and infinity, these are written as `null`.
+pub fn format_float(value: f64) -> String {
+    if !value.is_finite() {
+        return "null".to_owned();
+    }
+
+    let text = value.to_string();
+    if text.contains('.') || text.contains('e') {
+        text
+    } else {
+        format!("{}.0", text)
+    }
+}
+
+struct Writer<'a> {
+    tree: &'a Tree,
+    options: &'a WriteOptions,
+    out: String,
+}
+
+impl<'a> Writer<'a> {
+    fn newline(&mut self, depth: usize) {
+        if self.options.pretty {
+            self.out.push('\n');
+            (0..depth).for_each(|_| self.out.push_str(&self.options.indent));
+        }
+    }
+
+    fn write(&mut self, entry: &Entry, depth: usize) {
+        match &entry.entry_type {
+            EntryType::JSONObject(_) => {
+                let mut members = self.tree.object_entries(entry);
+                if self.options.sort_keys {
+                    members.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+                }
+
+                let colon = if self.options.pretty { ": " } else { ":" };
+                self.out.push('{');
+                members.iter().enumerate().for_each(|(idx, (key, value))| {
+                    if idx > 0 {
+                        self.out.push(',');
+                    }
+                    self.newline(depth + 1);
+                    self.out.push_str(&quote_string(&key.name));
+                    self.out.push_str(colon);
+                    self.write(value, depth + 1);
+                });
+                if !members.is_empty() {
+                    self.newline(depth);
+                }
+                self.out.push('}');
+            }
+            EntryType::JSONArray(_) => {
+                let values = self.tree.array_entries(entry);
+
+                self.out.push('[');
+                values.iter().enumerate().for_each(|(idx, value)| {
+                    if idx > 0 {
+                        self.out.push(',');
+                    }
+                    self.newline(depth + 1);
+                    self.write(value, depth + 1);
+                });
+                if !values.is_empty() {
+                    self.newline(depth);
+                }
+                self.out.push(']');
+            }
+            EntryType::String(value) => self.out.push_str(&quote_string(value)),
+            EntryType::Int(value) => self.out.push_str(&value.to_string()),
+            EntryType::Float(value) => self.out.push_str(&format_float(*value)),
+            EntryType::Bool(value) => self.out.push_str(&value.to_string()),
+            EntryType::Null => self.out.push_str("null"),
+        }
+    }
+}
+
+/// Serialize an entry of a tree and all of its children to json.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::{parse_json, tree::PathType, writer::{write_entry, WriteOptions}};
+/// let tree = parse_json("{ \"a\": [1, 2.5, \"x\"] }").unwrap();
+/// let entry = tree.value_at(&[PathType::Object("a")])[0];
+/// assert_eq!("[1,2.5,\"x\"]", write_entry(&tree, entry, &WriteOptions::compact()));
+/// ```
+pub fn write_entry(tree: &Tree, entry: &Entry, options: &WriteOptions) -> String {
+    let mut writer = Writer {
+        tree,
+        options,
+        out: String::new(),
+    };
+    writer.write(entry, 0);
+    writer.out
+}
+
+/// Serialize a whole tree to json.
+///
+/// **Example**
+/// ```
+/// # use json_position_parser::{parse_json, writer::{write_tree, WriteOptions}};
+/// let tree = parse_json("{ \"b\": {}, \"a\": [true, null] }").unwrap();
+/// assert_eq!(
+///     "{\n  \"b\": {},\n  \"a\": [\n    true,\n    null\n  ]\n}",
+///     write_tree(&tree, &WriteOptions::pretty("  "))
+/// );
+/// ```
+pub fn write_tree(tree: &Tree, options: &WriteOptions) -> String {
+    tree.root()
+        .map(|root| write_entry(tree, root, options))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quote_string, write_tree, WriteOptions};
+    use crate::parse_json;
+
+    #[test]
+    fn test_write_tree() {
+        let json = "{ \"z\": 1, \"a\": { \"q\\\"\": \"line\\nbreak\\u0001\" }, \"f\": [1.0, -2e3, false] }";
+        let tree = parse_json(json).unwrap();
+
+        let compact = write_tree(&tree, &WriteOptions::compact());
+        assert_eq!(
+            "{\"z\":1,\"a\":{\"q\\\"\":\"line\\nbreak\\u0001\"},\"f\":[1.0,-2000.0,false]}",
+            compact
+        );
+
+        // Writing what we parsed gives the same text again.
+        let reparsed = parse_json(&compact).unwrap();
+        assert_eq!(compact, write_tree(&reparsed, &WriteOptions::compact()));
+
+        let mut options = WriteOptions::pretty("\t");
+        options.sort_keys = true;
+        assert_eq!(
+            "{\n\t\"a\": {\n\t\t\"q\\\"\": \"line\\nbreak\\u0001\"\n\t},\n\t\"f\": [\n\t\t1.0,\n\t\t-2000.0,\n\t\tfalse\n\t],\n\t\"z\": 1\n}",
+            write_tree(&tree, &options)
+        );
+    }
+
+    #[test]
+    fn test_quote_string() {
+        assert_eq!(
+            "\"a\\\\b\\t\\u001f\u{e9}\"",
+            quote_string("a\\b\t\u{1f}\u{e9}")
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    id: i64,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_json-position-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Client {
+            child,
+            stdin,
+            stdout,
+            id: 0,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn respond(&mut self, method: &str, params: Value) -> Value {
+        self.id += 1;
+        let id = self.id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = self.receive();
+        assert_eq!(json!(id), response["id"]);
+        response
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.respond(method, params)["result"].clone()
+    }
+}
+
+const URI: &str = "file:///config.json";
+
+fn document() -> Value {
+    json!({ "uri": URI })
+}
+
+fn position(line: u32, character: u32) -> Value {
+    json!({ "line": line, "character": character })
+}
+
+#[test]
+fn test_lsp_session() {
+    let mut client = Client::start();
+
+    let result = client.request("initialize", json!({ "capabilities": {} }));
+    assert_eq!(json!(true), result["capabilities"]["hoverProvider"]);
+    client.notify("initialized", json!({}));
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": URI, "languageId": "jsonc", "version": 1, "text": "{ \"a\": 1 } x" } }),
+    );
+    let diagnostics = client.receive();
+    assert_eq!(
+        json!("textDocument/publishDiagnostics"),
+        diagnostics["method"]
+    );
+    assert_eq!(
+        json!({ "start": position(0, 11), "end": position(0, 12) }),
+        diagnostics["params"]["diagnostics"][0]["range"]
+    );
+
+    let text = "{\n  // ports\n  \"servers\": [{ \"port\": 80 }],\n  \"name\": \"é\"\n}";
+    client.notify(
+        "textDocument/didChange",
+        json!({ "textDocument": { "uri": URI, "version": 2 }, "contentChanges": [{ "text": text }] }),
+    );
+    let diagnostics = client.receive();
+    assert_eq!(json!([]), diagnostics["params"]["diagnostics"]);
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": document() }),
+    );
+    assert_eq!(json!("servers"), symbols[0]["name"]);
+    assert_eq!(
+        json!("port"),
+        symbols[0]["children"][0]["children"][0]["name"]
+    );
+    assert_eq!(json!("\"é\""), symbols[1]["detail"]);
+
+    let folding = client.request(
+        "textDocument/foldingRange",
+        json!({ "textDocument": document() }),
+    );
+    assert_eq!(
+        json!([{ "startLine": 0, "endLine": 4, "kind": "region" }]),
+        folding
+    );
+
+    let hover = client.request(
+        "textDocument/hover",
+        json!({ "textDocument": document(), "position": position(2, 20) }),
+    );
+    assert_eq!(
+        json!("`$.servers[0].port` (integer): `80`"),
+        hover["contents"]["value"]
+    );
+    assert_eq!(
+        json!({ "start": position(2, 16), "end": position(2, 22) }),
+        hover["range"]
+    );
+
+    let selection = client.request(
+        "textDocument/selectionRange",
+        json!({ "textDocument": document(), "positions": [position(2, 26)] }),
+    );
+    assert_eq!(
+        json!({ "start": position(2, 24), "end": position(2, 26) }),
+        selection[0]["range"]
+    );
+    assert_eq!(
+        json!({ "start": position(2, 16), "end": position(2, 26) }),
+        selection[0]["parent"]["range"]
+    );
+
+    let schema = "file:///schema.json";
+    client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": schema, "languageId": "json", "version": 1, "text": "{ \"s\": { \"$ref\": \"config.json#/servers\" } }" } }),
+    );
+    client.receive();
+    let definition = client.request(
+        "textDocument/definition",
+        json!({ "textDocument": { "uri": schema }, "position": position(0, 20) }),
+    );
+    assert_eq!(
+        json!({ "uri": URI, "range": { "start": position(2, 13), "end": position(2, 29) } }),
+        definition
+    );
+    let references = client.request(
+        "textDocument/references",
+        json!({ "textDocument": document(), "position": position(2, 5), "context": { "includeDeclaration": false } }),
+    );
+    assert_eq!(
+        json!([{ "uri": schema, "range": { "start": position(0, 17), "end": position(0, 39) } }]),
+        references
+    );
+
+    let edits = client.request(
+        "textDocument/formatting",
+        json!({ "textDocument": document(), "options": { "tabSize": 4, "insertSpaces": true } }),
+    );
+    assert!(!edits.as_array().unwrap().is_empty());
+
+    // Malformed params and unknown methods are answered with an error.
+    let response = client.respond("textDocument/hover", json!({ "textDocument": document() }));
+    assert_eq!(json!(-32602), response["error"]["code"]);
+    let response = client.respond("textDocument/unknown", json!({}));
+    assert_eq!(json!(-32601), response["error"]["code"]);
+
+    assert_eq!(Value::Null, client.request("shutdown", Value::Null));
+    client.notify("exit", Value::Null);
+    assert!(client.child.wait().unwrap().success());
+}
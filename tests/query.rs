@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn fixtures(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("json-position-query-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(
+        dir.join("a.json"),
+        "{\n  \"dependencies\": { \"lodash\": \"4.17\", \"serde\": \"1\" }\n}",
+    )
+    .unwrap();
+    fs::write(dir.join("sub/b.json"), "{ \"dependencies\": {} }").unwrap();
+    fs::write(dir.join("sub/notes.txt"), "not json").unwrap();
+    dir
+}
+
+fn query(args: &[&str]) -> (i32, String) {
+    let (status, stdout, _) = query_stderr(args);
+    (status, stdout)
+}
+
+fn query_stderr(args: &[&str]) -> (i32, String, String) {
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = Command::new(env!("CARGO_BIN_EXE_json-position-query"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        status.code().unwrap(),
+        String::from_utf8(stdout).unwrap(),
+        String::from_utf8(stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_query_files() {
+    let dir = fixtures("files");
+    let file = dir.join("a.json").display().to_string();
+    let dir = dir.display().to_string();
+
+    let (status, out) = query(&["$.dependencies.lodash", &dir]);
+    assert_eq!(0, status);
+    assert_eq!(format!("{}:2:31: \"4.17\"\n", file), out);
+
+    let (status, out) = query(&["--keys", "/dependencies", &file]);
+    assert_eq!(0, status);
+    assert_eq!(
+        format!("{}:2:21: lodash\n{}:2:39: serde\n", file, file),
+        out
+    );
+
+    let (status, out) = query(&["--json", "$..serde", &dir]);
+    assert_eq!(0, status);
+    assert_eq!(
+        format!(
+            "{{\"file\":\"{}\",\"line\":2,\"column\":48,\"path\":\"$.dependencies.serde\",\"pointer\":\"/dependencies/serde\",\"value\":\"1\"}}\n",
+            file
+        ),
+        out
+    );
+}
+
+#[test]
+fn test_query_exit_status() {
+    let dir = fixtures("status");
+    let file = dir.join("a.json").display().to_string();
+
+    assert_eq!((1, String::new()), query(&["$.missing", &file]));
+    assert_eq!(2, query(&["$[", &file]).0);
+    assert_eq!(2, query(&["$.dependencies", &file, "missing.json"]).0);
+    assert_eq!(2, query(&["$.dependencies"]).0);
+}
+
+#[test]
+fn test_query_skips_unparsable_files() {
+    let dir = fixtures("skip");
+    fs::write(dir.join("list.json"), "[1, 2]").unwrap();
+    fs::write(dir.join("sub/broken.json"), "{ \"a\": ").unwrap();
+    let file = dir.join("a.json").display().to_string();
+    let list = dir.join("list.json").display().to_string();
+
+    let (status, out, err) = query_stderr(&["$.dependencies.lodash", &dir.display().to_string()]);
+    assert_eq!(0, status);
+    assert_eq!(format!("{}:2:31: \"4.17\"\n", file), out);
+    assert!(err.contains(&format!("{}: skipped", list)), "{}", err);
+    assert!(err.contains("broken.json: skipped"), "{}", err);
+
+    assert_eq!(1, query(&["$.a", &list]).0);
+}